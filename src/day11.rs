@@ -1,42 +1,38 @@
 use std::fs;
 
-fn make_step(state: &mut [i8; 100]) -> usize {
+use crate::grid::Grid;
+
+type Octopuses = Grid<i8>;
+
+fn make_step(state: &mut Octopuses) -> usize {
     let mut num_flashes = 0;
     let mut flashes = Vec::new();
-    for y in 0..10_i8 {
-        for x in 0..10_i8 {
-            let off = (y * 10 + x) as usize;
-            state[off] += 1;
-            if state[off] == 10 {
-                flashes.push((x, y));
-                num_flashes += 1;
-            }
+    for pos in state.coords() {
+        state[pos] += 1;
+        if state[pos] == 10 {
+            flashes.push(pos);
+            num_flashes += 1;
         }
     }
     while let Some(flash) = flashes.pop() {
-        for y in flash.1 - 1..=flash.1 + 1 {
-            for x in flash.0 - 1..=flash.0 + 1 {
-                if x >= 0 && x < 10 && y >= 0 && y < 10 {
-                    let off = (y * 10 + x) as usize;
-                    state[off] += 1;
-                    if state[off] == 10 {
-                        flashes.push((x, y));
-                        num_flashes += 1;
-                    }
-                }
+        for pos in state.neighbours8(flash.0, flash.1).collect::<Vec<_>>() {
+            state[pos] += 1;
+            if state[pos] == 10 {
+                flashes.push(pos);
+                num_flashes += 1;
             }
         }
     }
-    for i in 0..100 {
-        if state[i] > 9 {
-            state[i] = 0;
+    for v in state.iter_mut() {
+        if *v > 9 {
+            *v = 0;
         }
     }
     num_flashes
 }
 
-fn solve_pt1(input: &[i8; 100]) -> usize {
-    let mut map = *input;
+fn solve_pt1(input: &Octopuses) -> usize {
+    let mut map = input.clone();
     let mut num_flashes = 0;
     for _ in 0..100 {
         num_flashes += make_step(&mut map);
@@ -44,13 +40,13 @@ fn solve_pt1(input: &[i8; 100]) -> usize {
     num_flashes
 }
 
-fn solve_pt2(input: &[i8; 100]) -> usize {
-    let mut map = *input;
+fn solve_pt2(input: &Octopuses) -> usize {
+    let mut map = input.clone();
     let mut step = 0;
     loop {
         step += 1;
         let num_flashes = make_step(&mut map);
-        if num_flashes == 100 {
+        if num_flashes == map.size_x() * map.size_y() {
             return step;
         }
     }
@@ -79,26 +75,16 @@ pub fn main() {
 }
 
 #[allow(dead_code)]
-fn to_string(state: &[i8; 100]) -> String {
-    let mut result = String::new();
-    for y in 0..10 {
-        let line: String = state[y * 10..y * 10 + 10]
-            .iter()
-            .cloned()
-            .map(|c| if c < 10 { c + '0' as i8 } else { c - 10 + 'A' as i8 } as u8 as char)
-            .collect();
-        result.push_str(&line);
-        result.push('\n');
-    }
-    result
+fn to_string(state: &Octopuses) -> String {
+    state.render(|&c| {
+        if c < 10 {
+            (c as u8 + b'0') as char
+        } else {
+            (c as u8 - 10 + b'A') as char
+        }
+    })
 }
 
-fn parse_input(s: &str) -> [i8; 100] {
-    <[i8; 100]>::try_from(
-        s.bytes()
-            .filter(|&c| c >= '0' as u8 && c <= '9' as u8)
-            .map(|c| (c - '0' as u8) as i8)
-            .collect::<Vec<_>>(),
-    )
-    .unwrap()
+fn parse_input(s: &str) -> Octopuses {
+    Grid::parse(s, |c| c.to_digit(10).unwrap() as i8)
 }
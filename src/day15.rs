@@ -1,22 +1,41 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::slice::Iter;
 use std::fmt;
+use std::time::Instant;
 
-#[derive(Debug)]
-struct Input {
-    size_x: usize,
-    size_y: usize,
-    map: Vec<Vec<usize>>
-}
+use crate::grid::Grid;
+
+type Input = Grid<usize>;
 
 #[derive(Debug)]
 #[derive(Clone)]
 enum Direction {Up, Down, Right, Left}
 
 #[derive(Debug)]
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct Point {x: isize, y: isize}
 
+#[derive(Debug, Clone, Copy)]
+enum Search {Dijkstra, AStar}
+
+impl Search {
+    // lower bound of the cost from pt to finish, every step costs at least 1 so manhattan distance is admissible
+    fn estimate(&self, pt: &Point, finish: &Point) -> usize {
+        match self {
+            Self::Dijkstra => 0,
+            Self::AStar => ((finish.x - pt.x).abs() + (finish.y - pt.y).abs()) as usize,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SearchResult {
+    cost: usize,
+    expanded: usize, // number of nodes taken from the queue
+    path: Vec<Point>, // from start to finish, both included
+}
+
 impl Direction {
     pub fn iterator() -> Iter<'static, Direction> {
         static DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Right, Direction::Left];
@@ -56,20 +75,16 @@ struct MapNode {
     visit: Option<VisitData>
 }
 
-struct Map {
-    size_x: usize,
-    size_y: usize,
-    map: Vec<MapNode>
-}
+struct Map(Grid<MapNode>);
 
 impl fmt::Debug for Map {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for y in 0..self.size_y as isize{
-            for x in 0..self.size_x as isize {
-                let node = self.get_at(&Point {x, y}).unwrap();
+        for y in 0..self.0.size_y() {
+            for x in 0..self.0.size_x() {
+                let node = &self.0[(x, y)];
                 write!(f, "[{:1} {:2}]", node.cost, node.visit.as_ref().map(|v| v.cost).unwrap_or(0))?;
             }
-            writeln!(f, "")?;
+            writeln!(f)?;
         }
         write!(f, "")
     }
@@ -77,47 +92,19 @@ impl fmt::Debug for Map {
 
 impl Map {
     fn from_input(input: &Input) -> Self {
-        let mut map = Vec::new();
-        let size_x = input.size_x;
-        let size_y = input.size_y;
-        for y in 0..size_y {
-            let input_line = &input.map[y];
-            for x in 0..size_x {
-                map.push(MapNode { cost: input_line[x], visit: None });
-            }
-        }
-        Map { size_x, size_y, map }
+        let map = input.iter().map(|&cost| MapNode { cost, visit: None }).collect();
+        Map(Grid::from_vec(input.size_x(), input.size_y(), map))
     }
 
     fn get_at(&self, pt: &Point) -> Option<&MapNode> {
-        if pt.x >= 0 && pt.y >= 0 {
-            let x = pt.x as usize;
-            let y = pt.y as usize;
-            if x < self.size_x && y < self.size_y {
-                return Some(&self.map[x + y * self.size_x]);
-            }
-        }
-        None
+        self.0.get(pt.x, pt.y)
     }
 
     fn set_visit_at(&mut self, pt: &Point, cost: usize, from: Direction) {
-        if pt.x >= 0 && pt.y >= 0 {
-            let x = pt.x as usize;
-            let y = pt.y as usize;
-            if x < self.size_x && y < self.size_y {
-                let node = &mut self.map[x + y * self.size_x];
-                return match &mut node.visit {
-                    Some(visit_data) => {
-                        visit_data.cost = cost;
-                        visit_data.from = from;
-                    }
-                    None => {
-                        node.visit = Some(VisitData { cost, from });
-                    }
-                }
-            }
+        match self.0.get_mut(pt.x, pt.y) {
+            Some(node) => node.visit = Some(VisitData { cost, from }),
+            None => panic!("bad coordinates {:?} size {} {}", pt, self.0.size_x(), self.0.size_y()),
         }
-        panic!("bad coordinates {:?} size {} {}", pt, self.size_x, self.size_y);
     }
 
     fn get_visit_at(&self, pt: &Point) -> Option<&VisitData> {
@@ -131,29 +118,41 @@ impl Map {
     fn get_cost_at(&self, pt: &Point) -> Option<usize> {
         self.get_at(pt).map(|node| node.cost)
     }
-}
 
-fn solve_pt1(input: &Input) -> usize {
+    // follow directions stored in visits back from finish to start
+    fn get_path(&self, start: &Point, finish: &Point) -> Vec<Point> {
+        let mut path = vec![*finish];
+        let mut pt = *finish;
+        while pt != *start {
+            pt = self.get_visit_at(&pt).unwrap().from.next_point(&pt);
+            path.push(pt);
+        }
+        path.reverse();
+        path
+    }
+}
 
+fn find_path(input: &Input, search: Search) -> Option<SearchResult> {
     let mut map = Map::from_input(input);
     let start = Point { x: 0, y: 0 };
-    let finish = Point { x: input.size_x as isize - 1, y: input.size_y as isize - 1 };
+    let finish = Point { x: input.size_x() as isize - 1, y: input.size_y() as isize - 1 };
     map.set_visit_at(&start, 0, Direction::Up);
-    let mut queue = vec![Point { x: start.x, y: start.y }];
 
-    let mut i = 0;
+    // queue items are (cost + estimate, cost, point), smallest first
+    let mut queue = BinaryHeap::new();
+    queue.push(Reverse((search.estimate(&start, &finish), 0, start)));
+    let mut expanded = 0;
 
-    while let Some(pt) = queue.pop() {
-
-        i += 1;
-        if i > 1000000 {
-            println!("{:?}", map);
-            break;
+    while let Some(Reverse((_, cost, pt))) = queue.pop() {
+        if cost > map.get_visit_cost_at(&pt).unwrap() {
+            // point was pushed again with lower cost and already processed
+            continue;
         }
-        let cost = map.get_visit_cost_at(&pt).unwrap();
         if pt == finish {
-            break;
+            let path = map.get_path(&start, &finish);
+            return Some(SearchResult { cost, expanded, path });
         }
+        expanded += 1;
         for direction in Direction::iterator() {
             let pt1 = direction.next_point(&pt);
             if let Some(cost_at1) = map.get_cost_at(&pt1) {
@@ -161,49 +160,129 @@ fn solve_pt1(input: &Input) -> usize {
                 let is_better = map.get_visit_cost_at(&pt1).map(|existing_cost1| cost1 < existing_cost1).unwrap_or(true);
                 if is_better {
                     map.set_visit_at(&pt1, cost1, direction.reverse());
-                    queue.push(pt1);
+                    queue.push(Reverse((cost1 + search.estimate(&pt1, &finish), cost1, pt1)));
                 }
             }
         }
-
-        // TODO: need sort in right order
-        queue.sort_by_key(|pt| map.get_visit_cost_at(pt).unwrap());
-        queue.reverse();
     }
 
-    map.get_visit_cost_at(&finish).unwrap()
+    None
 }
 
-fn solve_pt2(input: &Input) -> usize {
+fn solve_pt1(input: &Input) -> usize {
+    find_path(input, Search::AStar).expect("no path to finish").cost
+}
+
+fn make_pt2_input(input: &Input) -> Input {
 
     let n = 5;
-    let size_x = input.size_x * n;
-    let size_y = input.size_y * n;
-    let mut map = vec![vec![0; size_x]; size_y];
-    for y in 0..size_y {
-        let line = &mut map[y];
-        let ny = y / input.size_y;
-        let iy = y - ny * input.size_y;
-        for x in 0..size_x {
-            
-            let nx = x / input.size_x;
-            let ix = x - nx * input.size_x;
-            line[x] = ((input.map[iy][ix] + nx + ny) - 1) % 9 + 1;
+    let mut map = Grid::new(input.size_x() * n, input.size_y() * n, 0);
+    for (x, y) in map.coords() {
+        let nx = x / input.size_x();
+        let ix = x - nx * input.size_x();
+        let ny = y / input.size_y();
+        let iy = y - ny * input.size_y();
+        map[(x, y)] = ((input[(ix, iy)] + nx + ny) - 1) % 9 + 1;
+    }
+    map
+}
+
+fn solve_pt2(input: &Input) -> usize {
+    solve_pt1(&make_pt2_input(input))
+}
+
+// path cells show their risk level, other cells are dots
+fn render_path(input: &Input, path: &[Point]) -> String {
+    let on_path: HashSet<_> = path.iter().collect();
+    let mut result = String::new();
+    for y in 0..input.size_y() {
+        for x in 0..input.size_x() {
+            if on_path.contains(&Point { x: x as isize, y: y as isize }) {
+                result.push_str(&input[(x, y)].to_string());
+            } else {
+                result.push('.');
+            }
+        }
+        result.push('\n');
+    }
+    result
+}
+
+// binary ppm image, risk levels are shades of gray from white to black, path is red
+fn write_path_ppm(input: &Input, path: &[Point], filename: &std::path::Path) -> std::io::Result<()> {
+    let on_path: HashSet<_> = path.iter().collect();
+    let mut data = format!("P6\n{} {}\n255\n", input.size_x(), input.size_y()).into_bytes();
+    for y in 0..input.size_y() {
+        for x in 0..input.size_x() {
+            if on_path.contains(&Point { x: x as isize, y: y as isize }) {
+                data.extend_from_slice(&[255, 0, 0]);
+            } else {
+                let v = (255 - input[(x, y)] * 25) as u8;
+                data.extend_from_slice(&[v, v, v]);
+            }
         }
     }
-    solve_pt1(&Input {map, size_x, size_y})
+    std::fs::write(filename, data)
+}
+
+fn benchmark(input: &Input) {
+    let input = make_pt2_input(input);
+    for search in [Search::Dijkstra, Search::AStar] {
+        let start = Instant::now();
+        let result = find_path(&input, search).unwrap();
+        println!("day15 benchmark pt2 {:?} cost {} expanded {} in {:?}", search, result.cost, result.expanded, start.elapsed());
+    }
 }
 
 fn test(input: &Input) {
     assert_eq!(solve_pt1(input), 40);
     assert_eq!(solve_pt2(input), 315);
+    for search in [Search::Dijkstra, Search::AStar] {
+        assert_eq!(find_path(input, search).unwrap().cost, 40);
+        assert_eq!(find_path(&make_pt2_input(input), search).unwrap().cost, 315);
+    }
+
+    // path should go from start to finish by adjacent points and sum of its risk levels is the cost
+    for input in [input, &make_pt2_input(input)] {
+        let result = find_path(input, Search::AStar).unwrap();
+        assert_eq!(result.path.first(), Some(&Point { x: 0, y: 0 }));
+        assert_eq!(result.path.last(), Some(&Point { x: input.size_x() as isize - 1, y: input.size_y() as isize - 1 }));
+        for pair in result.path.windows(2) {
+            assert_eq!((pair[0].x - pair[1].x).abs() + (pair[0].y - pair[1].y).abs(), 1);
+        }
+        let path_cost: usize = result.path[1..].iter().map(|pt| input[(pt.x as usize, pt.y as usize)]).sum();
+        assert_eq!(path_cost, result.cost);
+        assert_eq!(render_path(input, &result.path).chars().filter(|c| c.is_ascii_digit()).count(), result.path.len());
+    }
+
+    // cheap corridor along the top row and right column, the heuristic keeps A* inside it
+    // while Dijkstra also expands expensive cells that are close to the start
+    let mut corridor = Grid::new(20, 20, 9);
+    for i in 0..20 {
+        corridor[(i, 0)] = 1;
+        corridor[(19, i)] = 1;
+    }
+    let dijkstra = find_path(&corridor, Search::Dijkstra).unwrap();
+    let astar = find_path(&corridor, Search::AStar).unwrap();
+    assert_eq!(dijkstra.cost, 38);
+    assert_eq!(astar.cost, 38);
+    // the corridor has 39 cells
+    assert!(astar.expanded <= 39);
+    assert!(dijkstra.expanded > 39);
 }
 
 pub fn main() {
     let input = read_input("input/day15.txt");
     test(input.get("test").unwrap());
+    let test_input = input.get("test").unwrap();
+    println!("test path:\n{}", render_path(test_input, &find_path(test_input, Search::AStar).unwrap().path));
     println!("day15 pt1 {}", solve_pt1(input.get("day15").unwrap()));
     println!("day15 pt2 {}", solve_pt2(input.get("day15").unwrap()));
+    let pt2_input = make_pt2_input(input.get("day15").unwrap());
+    let ppm_filename = std::env::temp_dir().join("day15_pt2_path.ppm");
+    write_path_ppm(&pt2_input, &find_path(&pt2_input, Search::AStar).unwrap().path, &ppm_filename).unwrap();
+    println!("day15 pt2 path image {}", ppm_filename.display());
+    benchmark(input.get("day15").unwrap());
 }
 
 fn read_input(filename: &str) -> HashMap<String, Input> {
@@ -211,27 +290,11 @@ fn read_input(filename: &str) -> HashMap<String, Input> {
     let s = std::fs::read_to_string(filename).unwrap();
     let mut lines = s.split("\n").map(|s| s.trim());
     while let Some(name) = lines.next() {
-        let mut map = Vec::new();
-        let mut size_x = None;
-        while let Some(line) = lines.next() {
-            if line.len() == 0 {
-                break;
-            }
-            let map_line = line
-                .chars()
-                .map(|c| c.to_string().parse())
-                .flatten()
-                .collect::<Vec<_>>();
-            let line_size_x = map_line.len();
-            match size_x {
-                None => size_x = Some(line_size_x),
-                Some(size_x) => assert_eq!(size_x, line_size_x)
-            }
-            map.push(map_line);
-        }
-        let size_x = size_x.unwrap();
-        let size_y = map.len();
-        result.insert(name.to_string(), Input { map, size_x, size_y });
+        let map = Grid::parse_lines(
+            lines.by_ref().take_while(|line| !line.is_empty()),
+            |c| c.to_digit(10).unwrap() as usize
+        );
+        result.insert(name.to_string(), map);
     }
     result
 }
@@ -35,15 +35,48 @@ Z
 use std::{
     collections::{HashMap, HashSet},
     fmt,
+    fmt::Write,
     ops::{Index, IndexMut},
 };
 
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
 use Axis::*;
 
 type Input = HashMap<String, Vec<Vector>>;
 
 const MIN_PAIR: usize = 12;
 
+// parameters used to decide whether two scanners overlap
+#[derive(Clone, Copy, Debug)]
+struct AlignParams {
+    min_pair: usize, // minimal number of common beacons
+    tolerance: i32, // beacons within this manhattan distance are the same beacon, 0 means exact match
+}
+
+impl AlignParams {
+    fn exact(min_pair: usize) -> AlignParams {
+        AlignParams {
+            min_pair,
+            tolerance: 0,
+        }
+    }
+
+    fn with_tolerance(min_pair: usize, tolerance: i32) -> AlignParams {
+        assert!(tolerance >= 0);
+        AlignParams {
+            min_pair,
+            tolerance,
+        }
+    }
+}
+
+impl Default for AlignParams {
+    fn default() -> Self {
+        AlignParams::exact(MIN_PAIR)
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Axis {
     X = 0,
@@ -64,6 +97,18 @@ impl Vector {
     fn negate(&self) -> Vector {
         Vector([-self[X], -self[Y], -self[Z]])
     }
+
+    fn manhattan_dist(&self, other: &Vector) -> i32 {
+        (self[X] - other[X]).abs() + (self[Y] - other[Y]).abs() + (self[Z] - other[Z]).abs()
+    }
+
+    fn chebyshev_dist(&self, other: &Vector) -> i32 {
+        Axis::all()
+            .into_iter()
+            .map(|axis| (self[axis] - other[axis]).abs())
+            .max()
+            .unwrap()
+    }
 }
 
 impl fmt::Debug for Vector {
@@ -173,6 +218,17 @@ impl Matrix {
     fn extract_translate(&self) -> Vector {
         Vector([self.0[0][3], self.0[1][3], self.0[2][3]])
     }
+
+    // inverse of rotation + translation: rotation is transposed, translation is rotated back and negated
+    fn inverse_rigid(&self) -> Matrix {
+        let m = &self.0;
+        let rotation = Matrix([
+            [m[0][0], m[1][0], m[2][0], 0],
+            [m[0][1], m[1][1], m[2][1], 0],
+            [m[0][2], m[1][2], m[2][2], 0],
+        ]);
+        rotation.mul(&Matrix::translate(&self.extract_translate().negate()))
+    }
 }
 
 #[derive(Clone)]
@@ -187,26 +243,62 @@ impl Scanner {
         Scanner(self.0.iter().map(|v| transform.apply(v)).collect())
     }
 
-    fn count_common(&self, other: &Scanner, other_transform: &Matrix) -> usize {
+    fn contains_near(&self, v: &Vector, tolerance: i32) -> bool {
+        if tolerance == 0 {
+            self.0.contains(v)
+        } else {
+            self.0.iter().any(|b| b.manhattan_dist(v) <= tolerance)
+        }
+    }
+
+    fn count_common(&self, other: &Scanner, other_transform: &Matrix, tolerance: i32) -> usize {
         other
             .0
             .iter()
-            .cloned()
-            .filter(|v| self.0.contains(&other_transform.apply(v)))
+            .filter(|&v| self.contains_near(&other_transform.apply(v), tolerance))
             .count()
     }
 }
 
-fn try_align(scanner0: &Scanner, scanner1: &Scanner) -> Option<Matrix> {
+// count beacons treating beacons within tolerance distance as the same beacon
+// with noise the same beacon seen by several scanners forms a chain of close points, so count connected groups
+fn count_unique<'a>(beacons: impl Iterator<Item = &'a Vector>, tolerance: i32) -> usize {
+    let beacons = beacons.collect::<HashSet<_>>();
+    if tolerance == 0 {
+        return beacons.len();
+    }
+    let beacons = beacons.into_iter().collect::<Vec<_>>();
+    let mut visited = vec![false; beacons.len()];
+    let mut count = 0;
+    for start in 0..beacons.len() {
+        if visited[start] {
+            continue;
+        }
+        count += 1;
+        visited[start] = true;
+        let mut queue = vec![start];
+        while let Some(i) = queue.pop() {
+            for j in 0..beacons.len() {
+                if !visited[j] && beacons[i].manhattan_dist(beacons[j]) <= tolerance {
+                    visited[j] = true;
+                    queue.push(j);
+                }
+            }
+        }
+    }
+    count
+}
+
+fn try_align(scanner0: &Scanner, scanner1: &Scanner, params: &AlignParams) -> Option<Matrix> {
     // align scanner0 and scanner1 on all positions and try all oriatations in every position
     for pos0 in scanner0.0.iter() {
         for pos1 in scanner1.0.iter() {
             let pre_orient = Matrix::translate(&pos1.negate());
-            let post_orient = Matrix::translate(&pos0);
+            let post_orient = Matrix::translate(pos0);
             for orientation in Matrix::all_orientations() {
                 let transform = post_orient.mul(&orientation).mul(&pre_orient);
-                let count = scanner0.count_common(scanner1, &transform);
-                if count >= MIN_PAIR {
+                let count = scanner0.count_common(scanner1, &transform, params.tolerance);
+                if count >= params.min_pair {
                     return Some(transform);
                 }
             }
@@ -215,16 +307,16 @@ fn try_align(scanner0: &Scanner, scanner1: &Scanner) -> Option<Matrix> {
     None
 }
 
-fn solve_both_parts(scanners: &[Scanner]) -> (usize, usize) {
+fn solve_both_parts(scanners: &[Scanner], params: &AlignParams) -> (usize, usize) {
     let mut knowns = vec![scanners[0].clone()];
     let mut known_positions = vec![Vector([0, 0, 0])];
     let mut known_queue = vec![knowns[0].clone()];
     let mut unknowns = scanners[1..].iter().collect::<Vec<_>>();
 
-    while unknowns.len() > 0 {
+    while !unknowns.is_empty() {
         let known = known_queue.pop().unwrap();
         unknowns.retain(|&unknown| {
-            if let Some(transform) = try_align(&known, unknown) {
+            if let Some(transform) = try_align(&known, unknown, params) {
                 let transformed_unknown = unknown.apply_transform(&transform);
                 knowns.push(transformed_unknown.clone());
                 known_queue.push(transformed_unknown);
@@ -235,16 +327,12 @@ fn solve_both_parts(scanners: &[Scanner]) -> (usize, usize) {
             }
         })
     }
-    let count = Scanner(knowns.iter().map(|s| s.0.clone()).flatten().collect())
-        .0
-        .len();
+    let count = count_unique(knowns.iter().flat_map(|s| s.0.iter()), params.tolerance);
 
     let mut max_dist = 0;
     for i in 0..known_positions.len() {
         for j in i + 1..known_positions.len() {
-            let a = &known_positions[i];
-            let b = &known_positions[j];
-            let dist = ((a[X] - b[X]).abs() + (a[Y] - b[Y]).abs() + (a[Z] - b[Z]).abs()) as usize;
+            let dist = known_positions[i].manhattan_dist(&known_positions[j]) as usize;
             if dist > max_dist {
                 max_dist = dist;
             }
@@ -254,6 +342,147 @@ fn solve_both_parts(scanners: &[Scanner]) -> (usize, usize) {
     (count, max_dist)
 }
 
+struct GenerateParams {
+    scanner_count: usize,
+    extra_beacon_count: usize, // beacons placed randomly in addition to ones guaranteeing overlap
+    range: i32,                // scanner sees beacons at most this far on every axis
+    min_pair: usize, // every scanner shares at least this many beacons with some previous scanner
+    noise: i32,      // reported coordinates are randomly off by at most this value on every axis
+}
+
+impl Default for GenerateParams {
+    fn default() -> Self {
+        GenerateParams {
+            scanner_count: 5,
+            extra_beacon_count: 50,
+            range: 1000,
+            min_pair: MIN_PAIR,
+            noise: 0,
+        }
+    }
+}
+
+struct GeneratedReport {
+    report: String,       // scanner reports in the same format as puzzle input
+    poses: Vec<Matrix>,   // transforms from scanner coordinates to scanner 0 coordinates
+    beacons: Vec<Vector>, // all beacons seen by at least one scanner, in scanner 0 coordinates
+}
+
+impl GeneratedReport {
+    fn max_scanner_dist(&self) -> usize {
+        let positions = self
+            .poses
+            .iter()
+            .map(|m| m.extract_translate())
+            .collect::<Vec<_>>();
+        let mut max_dist = 0;
+        for i in 0..positions.len() {
+            for j in i + 1..positions.len() {
+                max_dist = std::cmp::max(max_dist, positions[i].manhattan_dist(&positions[j]));
+            }
+        }
+        max_dist as usize
+    }
+}
+
+fn generate_report(params: &GenerateParams, rng: &mut StdRng) -> GeneratedReport {
+    let range = params.range;
+    let random_in_box = |rng: &mut StdRng, from: &Vector, to: &Vector| {
+        Vector([
+            rng.gen_range(from[X]..=to[X]),
+            rng.gen_range(from[Y]..=to[Y]),
+            rng.gen_range(from[Z]..=to[Z]),
+        ])
+    };
+
+    // scanner 0 defines coordinates, every next scanner is placed near one of previous scanners
+    // and overlapping region of two scanners gets min_pair beacons, so all scanners can be aligned
+    let mut positions = vec![Vector([0, 0, 0])];
+    let mut beacons = HashSet::new();
+    for _ in 1..params.scanner_count {
+        let parent = positions.choose(rng).unwrap().clone();
+        let offset = random_in_box(
+            rng,
+            &Vector([-range, -range, -range]),
+            &Vector([range, range, range]),
+        );
+        let position = Matrix::translate(&offset).apply(&parent);
+        let mut from = Vector([0, 0, 0]);
+        let mut to = Vector([0, 0, 0]);
+        for axis in Axis::all() {
+            from[axis] = std::cmp::max(parent[axis], position[axis]) - range;
+            to[axis] = std::cmp::min(parent[axis], position[axis]) + range;
+        }
+        while beacons.iter().filter(|&b| is_in_box(b, &from, &to)).count() < params.min_pair {
+            beacons.insert(random_in_box(rng, &from, &to));
+        }
+        positions.push(position);
+    }
+
+    // fill whole space covered by scanners with random beacons
+    let mut from = Vector([0, 0, 0]);
+    let mut to = Vector([0, 0, 0]);
+    for axis in Axis::all() {
+        from[axis] = positions.iter().map(|p| p[axis]).min().unwrap() - range;
+        to[axis] = positions.iter().map(|p| p[axis]).max().unwrap() + range;
+    }
+    for _ in 0..params.extra_beacon_count {
+        beacons.insert(random_in_box(rng, &from, &to));
+    }
+
+    let all_orientations = Matrix::all_orientations();
+    let mut report = String::new();
+    let mut poses = vec![];
+    for (n, position) in positions.iter().enumerate() {
+        let orientation = if n == 0 {
+            Matrix::identity()
+        } else {
+            all_orientations.choose(rng).unwrap().clone()
+        };
+        let pose = Matrix::translate(position).mul(&orientation);
+        let to_scanner = pose.inverse_rigid();
+        writeln!(report, "--- scanner {} ---", n).unwrap();
+        for beacon in beacons.iter() {
+            if beacon.chebyshev_dist(position) <= range {
+                let mut v = to_scanner.apply(beacon);
+                for axis in Axis::all() {
+                    v[axis] += rng.gen_range(-params.noise..=params.noise);
+                }
+                writeln!(report, "{},{},{}", v[X], v[Y], v[Z]).unwrap();
+            }
+        }
+        writeln!(report).unwrap();
+        poses.push(pose);
+    }
+
+    let beacons = beacons
+        .into_iter()
+        .filter(|b| positions.iter().any(|p| b.chebyshev_dist(p) <= range))
+        .collect();
+
+    GeneratedReport {
+        report,
+        poses,
+        beacons,
+    }
+}
+
+fn is_in_box(v: &Vector, from: &Vector, to: &Vector) -> bool {
+    Axis::all()
+        .into_iter()
+        .all(|axis| v[axis] >= from[axis] && v[axis] <= to[axis])
+}
+
+fn scanners_from_input(input: &Input) -> Vec<Scanner> {
+    let mut scanners = vec![];
+    let mut i = 0;
+    while let Some(vec) = input.get(&format!("scanner {}", i)) {
+        scanners.push(Scanner::from_vec(vec));
+        i += 1;
+    }
+    scanners
+}
+
 pub fn main() {
     let input = parse_input(&std::fs::read_to_string("input/day19.txt").unwrap());
     test_matrices();
@@ -261,16 +490,16 @@ pub fn main() {
     test_matrix_translate();
     test0(input.get("test0").unwrap());
     test1(input.get("test1").unwrap());
+    test_align_params(input.get("test1").unwrap());
+    test_inverse_rigid();
+    test_generated();
     println!("tests ok");
 
-    let mut day19_scanners = vec![];
-    let mut i = 0;
-    let day19_input = input.get("day19").unwrap();
-    while let Some(vec) = day19_input.get(&format!("scanner {}", i)) {
-        day19_scanners.push(Scanner::from_vec(vec));
-        i += 1;
-    }
-    println!("day19 pt1, pt2: {:?}", solve_both_parts(&day19_scanners));
+    let day19_scanners = scanners_from_input(input.get("day19").unwrap());
+    println!(
+        "day19 pt1, pt2: {:?}",
+        solve_both_parts(&day19_scanners, &AlignParams::default())
+    );
 }
 
 fn test1(test1_input: &Input) {
@@ -279,19 +508,115 @@ fn test1(test1_input: &Input) {
         .map(|v| Scanner::from_vec(v))
         .collect::<Vec<_>>();
 
+    let params = AlignParams::default();
+
     // try align scanners 0 and 1
-    let align_result = try_align(&scanners[0], &scanners[1]).unwrap();
+    let align_result = try_align(&scanners[0], &scanners[1], &params).unwrap();
     let transformed_1 = scanners[1].apply_transform(&align_result);
     assert_eq!(
-        try_align(&scanners[0], &transformed_1),
+        try_align(&scanners[0], &transformed_1, &params),
         Some(Matrix::identity())
     );
     assert_eq!(align_result.extract_translate().0, [68, -1246, -43]);
 
-    let align_result = try_align(&transformed_1, &scanners[4]).unwrap();
+    let align_result = try_align(&transformed_1, &scanners[4], &params).unwrap();
     assert_eq!(align_result.extract_translate().0, [-20, -1133, 1061]);
 
-    assert_eq!(solve_both_parts(&scanners), (79, 3621));
+    assert_eq!(solve_both_parts(&scanners, &params), (79, 3621));
+}
+
+fn test_align_params(test1_input: &Input) {
+    let scanners = (0..=4)
+        .map(|n| test1_input.get(&format!("scanner {}", n)).unwrap())
+        .map(|v| Scanner::from_vec(v))
+        .collect::<Vec<_>>();
+
+    // scanners 0 and 1 have exactly 12 beacons in common
+    assert!(try_align(&scanners[0], &scanners[1], &AlignParams::exact(12)).is_some());
+    assert!(try_align(&scanners[0], &scanners[1], &AlignParams::exact(13)).is_none());
+
+    // move every beacon by 1 along one of the axes, exact matching can not align such scanners
+    // every alignment can be off by 2, and two observations of the same beacon by 2 more
+    let noisy_scanners = (0..=4)
+        .map(|n| test1_input.get(&format!("scanner {}", n)).unwrap())
+        .map(|v| {
+            v.iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let mut v = v.clone();
+                    v[Axis::all()[i % 3]] += if i % 2 == 0 { 1 } else { -1 };
+                    v
+                })
+                .collect::<Vec<_>>()
+        })
+        .map(|v| Scanner::from_vec(&v))
+        .collect::<Vec<_>>();
+    assert!(try_align(
+        &noisy_scanners[0],
+        &noisy_scanners[1],
+        &AlignParams::default()
+    )
+    .is_none());
+
+    let params = AlignParams::with_tolerance(12, 4);
+    let align_result = try_align(&noisy_scanners[0], &noisy_scanners[1], &params).unwrap();
+    assert!(
+        align_result
+            .extract_translate()
+            .manhattan_dist(&Vector([68, -1246, -43]))
+            <= 2
+    );
+    let (count, max_dist) = solve_both_parts(&noisy_scanners, &params);
+    assert_eq!(count, 79);
+    assert!((max_dist as isize - 3621).abs() <= 4);
+}
+
+fn test_generated() {
+    for seed in 0..10 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let generated = generate_report(&GenerateParams::default(), &mut rng);
+        let input = parse_input(&format!("generated\n{}", generated.report));
+        let scanners = scanners_from_input(input.get("generated").unwrap());
+        assert_eq!(scanners.len(), generated.poses.len());
+
+        // every scanner report transformed with its pose should give original beacons
+        for (scanner, pose) in scanners.iter().zip(generated.poses.iter()) {
+            let transformed = scanner.apply_transform(pose);
+            assert!(transformed.0.iter().all(|b| generated.beacons.contains(b)));
+        }
+
+        assert_eq!(
+            solve_both_parts(&scanners, &AlignParams::default()),
+            (generated.beacons.len(), generated.max_scanner_dist()),
+            "seed {}",
+            seed
+        );
+    }
+
+    // noisy reports can be solved with tolerance
+    // every coordinate is off by 1, so every alignment is off by up to 6 and errors add up along the chain of scanners
+    for seed in 0..5 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let params = GenerateParams {
+            noise: 1,
+            ..Default::default()
+        };
+        let generated = generate_report(&params, &mut rng);
+        let input = parse_input(&format!("generated\n{}", generated.report));
+        let scanners = scanners_from_input(input.get("generated").unwrap());
+        let (count, max_dist) = solve_both_parts(&scanners, &AlignParams::with_tolerance(12, 10));
+        assert_eq!(count, generated.beacons.len(), "seed {}", seed);
+        assert!((max_dist as isize - generated.max_scanner_dist() as isize).abs() <= 12);
+    }
+}
+
+fn test_inverse_rigid() {
+    let v = Vector([1, 2, 3]);
+    for orientation in Matrix::all_orientations() {
+        let m = Matrix::translate(&Vector([10, -20, 30])).mul(&orientation);
+        assert_eq!(m.inverse_rigid().apply(&m.apply(&v)), v);
+        assert_eq!(m.mul(&m.inverse_rigid()), Matrix::identity());
+    }
 }
 
 fn test0(test0_input: &Input) {
@@ -407,13 +732,13 @@ fn parse_input(s: &str) -> HashMap<String, Input> {
     while let Some(input_name) = lines.next() {
         let mut input = HashMap::new();
         while let Some(scanner) = lines.next() {
-            if scanner.len() == 0 {
+            if scanner.is_empty() {
                 break;
             }
             let scanner_name = &scanner[4..scanner.len() - 4];
             let mut scanner_vecs = vec![];
-            while let Some(vec) = lines.next() {
-                if vec.len() == 0 {
+            for vec in lines.by_ref() {
+                if vec.is_empty() {
                     break;
                 }
                 let vec = Vector(
@@ -1,5 +1,8 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::collections::HashMap;
+use std::ops::{AddAssign, Mul};
 
+#[derive(Debug, PartialEq)]
 struct Rules {
     rolls: usize,
     dice_sides: usize,
@@ -7,75 +10,43 @@ struct Rules {
     max_score: usize,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct PlayerState {
     pos: usize,
     score: usize,
     is_current: bool,
 }
 
-type GameState = [PlayerState; 2];
+type GameState = Vec<PlayerState>;
 
-fn make_next_state(state: &GameState, dice_sum: usize, rules: &Rules) -> GameState {
-    fn make_next_player_state(state: &PlayerState, dice_sum: usize, rules: &Rules) -> PlayerState {
-        if state.is_current {
-            let pos = (state.pos + dice_sum) % rules.board_size;
-            PlayerState {
-                pos: pos,
-                score: state.score + pos + 1,
-                is_current: !state.is_current,
-            }
-        } else {
-            PlayerState {
-                pos: state.pos,
-                score: state.score,
-                is_current: !state.is_current,
-            }
-        }
-    }
-
-    [
-        make_next_player_state(&state[0], dice_sum, rules),
-        make_next_player_state(&state[1], dice_sum, rules),
-    ]
+// positions are 1 based as in the puzzle text, first player moves first
+fn make_start_state(positions: &[usize]) -> GameState {
+    positions
+        .iter()
+        .enumerate()
+        .map(|(n, pos)| PlayerState {
+            pos: pos - 1,
+            score: 0,
+            is_current: n == 0,
+        })
+        .collect()
 }
 
-fn get_winning_player(state: &GameState, rules: &Rules) -> Option<usize> {
-    for n in 0..state.len() {
-        if state[n].score >= rules.max_score {
-            return Some(n);
-        }
-    }
-    None
+fn make_next_state(state: &GameState, dice_sum: usize, rules: &Rules) -> GameState {
+    let current = state.iter().position(|player| player.is_current).unwrap();
+    let mut next_state = state.clone();
+    let player = &mut next_state[current];
+    player.pos = (player.pos + dice_sum) % rules.board_size;
+    player.score += player.pos + 1;
+    player.is_current = false;
+    next_state[(current + 1) % state.len()].is_current = true;
+    next_state
 }
 
-fn play_game_determenistic_dice(pos1: usize, pos2: usize, rules: &Rules) -> usize {
-    let mut state = [
-        PlayerState {
-            is_current: true,
-            pos: pos1 - 1,
-            score: 0,
-        },
-        PlayerState {
-            is_current: false,
-            pos: pos2 - 1,
-            score: 0,
-        },
-    ];
-    let mut roll_count = 0_usize;
-    while get_winning_player(&state, rules) == None {
-        let mut dice_sum = 0;
-        for _ in 0..3 {
-            dice_sum += roll_count % rules.dice_sides + 1;
-            roll_count += 1;
-        }
-        state = make_next_state(&state, dice_sum, rules);
-        //println!("{:?}", state);
-    }
-    let winning_player = get_winning_player(&state, rules).unwrap();
-    let other_player = (winning_player + 1) % 2;
-    let other_score = state[other_player].score;
-    other_score * roll_count
+fn get_winning_player(state: &GameState, rules: &Rules) -> Option<usize> {
+    state
+        .iter()
+        .position(|player| player.score >= rules.max_score)
 }
 
 fn make_all_dice_rolls(rules: &Rules) -> HashMap<usize, usize> {
@@ -94,62 +65,519 @@ fn make_all_dice_rolls(rules: &Rules) -> HashMap<usize, usize> {
     results
 }
 
-fn play_game_quantum_dice(pos1: usize, pos2: usize, rules: &Rules) -> [usize; 2] {
-    let all_rolls = make_all_dice_rolls(rules);
-    //println!("{:?}", all_rolls);
-    let state = [
-        PlayerState {
-            pos: pos1 - 1,
-            score: 0,
-            is_current: true,
-        },
-        PlayerState {
-            pos: pos2 - 1,
-            score: 0,
-            is_current: false,
-        },
-    ];
+// dice used for a whole turn, yields every possible sum of rules.rolls rolls
+// with number of universes where it happens
+trait Dice {
+    fn roll(&mut self, rules: &Rules) -> Vec<(usize, usize)>;
+}
+
+// 1, 2, 3, ... dice_sides, 1, 2, ...
+#[derive(Default)]
+struct DeterministicDice {
+    roll_count: usize,
+}
+
+impl DeterministicDice {
+    fn roll_sum(&mut self, rules: &Rules) -> usize {
+        let mut dice_sum = 0;
+        for _ in 0..rules.rolls {
+            dice_sum += self.roll_count % rules.dice_sides + 1;
+            self.roll_count += 1;
+        }
+        dice_sum
+    }
+}
+
+impl Dice for DeterministicDice {
+    fn roll(&mut self, rules: &Rules) -> Vec<(usize, usize)> {
+        vec![(self.roll_sum(rules), 1)]
+    }
+}
+
+// every roll splits the universe into dice_sides copies
+struct QuantumDice {
+    all_rolls: Vec<(usize, usize)>,
+}
 
+impl QuantumDice {
+    fn new(rules: &Rules) -> QuantumDice {
+        QuantumDice {
+            all_rolls: make_all_dice_rolls(rules).into_iter().collect(),
+        }
+    }
+}
+
+impl Dice for QuantumDice {
+    fn roll(&mut self, _rules: &Rules) -> Vec<(usize, usize)> {
+        self.all_rolls.clone()
+    }
+}
+
+struct RandomDice {
+    rng: StdRng,
+}
+
+impl RandomDice {
+    fn new(seed: u64) -> RandomDice {
+        RandomDice {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn roll_sum(&mut self, rules: &Rules) -> usize {
+        (0..rules.rolls)
+            .map(|_| self.rng.gen_range(1..=rules.dice_sides))
+            .sum()
+    }
+}
+
+impl Dice for RandomDice {
+    fn roll(&mut self, rules: &Rules) -> Vec<(usize, usize)> {
+        vec![(self.roll_sum(rules), 1)]
+    }
+}
+
+// returns final states of all universes with number of universes ending in each state
+fn play_game(positions: &[usize], rules: &Rules, dice: &mut dyn Dice) -> HashMap<GameState, usize> {
     let mut states: HashMap<GameState, usize> = HashMap::new();
-    states.insert(state, 1);
-    let mut wins = [0; 2];
+    states.insert(make_start_state(positions), 1);
+    let mut finished = HashMap::new();
 
-    while states.len() > 0 {
+    while !states.is_empty() {
         let mut new_states = HashMap::new();
-        for (dice_sum, dice_sum_count) in all_rolls.iter() {
+        for (dice_sum, dice_sum_count) in dice.roll(rules) {
             for (state, state_count) in states.iter() {
-                let next_state = make_next_state(state, *dice_sum, rules);
-                if let Some(winning_player) = get_winning_player(&next_state, rules) {
-                    wins[winning_player] += state_count * dice_sum_count;
+                let next_state = make_next_state(state, dice_sum, rules);
+                let new_states = if get_winning_player(&next_state, rules).is_some() {
+                    &mut finished
                 } else {
-                    let new_count =
-                        new_states.get(&next_state).unwrap_or(&0) + state_count * dice_sum_count;
-                    new_states.insert(next_state, new_count);
-                }
+                    &mut new_states
+                };
+                let new_count =
+                    new_states.get(&next_state).unwrap_or(&0) + state_count * dice_sum_count;
+                new_states.insert(next_state, new_count);
             }
         }
         states = new_states;
     }
 
+    finished
+}
+
+// same as play_game for dice that never split the universe, state is changed in place,
+// returns final state and winning player
+fn play_single_game<F>(positions: &[usize], rules: &Rules, mut roll_sum: F) -> (GameState, usize)
+where
+    F: FnMut() -> usize,
+{
+    let mut state = make_start_state(positions);
+    let mut current = 0;
+    loop {
+        let player = &mut state[current];
+        player.pos = (player.pos + roll_sum()) % rules.board_size;
+        player.score += player.pos + 1;
+        player.is_current = false;
+        let score = player.score;
+        let next = (current + 1) % state.len();
+        state[next].is_current = true;
+        if score >= rules.max_score {
+            return (state, current);
+        }
+        current = next;
+    }
+}
+
+fn count_player_wins(finished: &HashMap<GameState, usize>, rules: &Rules) -> Vec<usize> {
+    let mut wins = Vec::new();
+    for (state, count) in finished.iter() {
+        wins.resize(state.len(), 0);
+        wins[get_winning_player(state, rules).unwrap()] += count;
+    }
     wins
 }
 
-pub fn main() {
-    let rules0 = Rules {
-        board_size: 10,
-        dice_sides: 100,
-        max_score: 1000,
-        rolls: 3,
+// lowest score among players who did not win, multiplied by number of rolls
+fn play_game_determenistic_dice(positions: &[usize], rules: &Rules) -> usize {
+    let mut dice = DeterministicDice::default();
+    let (state, winning_player) = play_single_game(positions, rules, || dice.roll_sum(rules));
+    let other_score = state
+        .iter()
+        .enumerate()
+        .filter(|(n, _)| *n != winning_player)
+        .map(|(_, player)| player.score)
+        .min()
+        .unwrap();
+    other_score * dice.roll_count
+}
+
+// number of universes in which each player wins
+fn play_game_quantum_dice(positions: &[usize], rules: &Rules) -> Vec<usize> {
+    let finished = play_game(positions, rules, &mut QuantumDice::new(rules));
+    count_player_wins(&finished, rules)
+}
+
+// winning player of a single game
+fn play_game_random_dice(positions: &[usize], rules: &Rules, dice: &mut RandomDice) -> usize {
+    play_single_game(positions, rules, || dice.roll_sum(rules)).1
+}
+
+// wins of each player in games played with a random dice
+fn simulate(positions: &[usize], rules: &Rules, games: usize, seed: u64) -> Vec<usize> {
+    let mut dice = RandomDice::new(seed);
+    let mut wins = vec![0; positions.len()];
+    for _ in 0..games {
+        wins[play_game_random_dice(positions, rules, &mut dice)] += 1;
+    }
+    wins
+}
+
+// win probability of each player with half width of confidence interval for given z score
+fn estimate_win_probabilities(wins: &[usize], z: f64) -> Vec<(f64, f64)> {
+    let games = wins.iter().sum::<usize>() as f64;
+    wins.iter()
+        .map(|&wins| {
+            let probability = wins as f64 / games;
+            let margin = z * (probability * (1.0 - probability) / games).sqrt();
+            (probability, margin)
+        })
+        .collect()
+}
+
+// universes are not equally likely: a game that lasts one more turn is split into
+// dice_sides ^ rolls more universes, so long games are overrepresented in win counts
+fn universe_win_ratios(positions: &[usize], rules: &Rules) -> Vec<f64> {
+    let wins = play_game_quantum_dice_memo(positions, rules);
+    let universes = wins.iter().sum::<usize>() as f64;
+    wins.iter().map(|&wins| wins as f64 / universes).collect()
+}
+
+fn exact_win_probabilities(positions: &[usize], rules: &Rules) -> Vec<f64> {
+    solve_wins(
+        &make_start_state(positions),
+        &make_all_dice_rolls(rules),
+        rules,
+        &mut HashMap::new(),
+    )
+}
+
+fn print_simulation(positions: &[usize], rules: &Rules, games: usize, seed: u64) {
+    let wins = simulate(positions, rules, games, seed);
+    let estimates = estimate_win_probabilities(&wins, 1.96);
+    let exact = exact_win_probabilities(positions, rules);
+    let ratios = universe_win_ratios(positions, rules);
+    println!("simulation of {} games from {:?}", games, positions);
+    for (player, (((probability, margin), exact), ratio)) in
+        estimates.iter().zip(exact).zip(ratios).enumerate()
+    {
+        println!(
+            "player {} wins {:.4} +- {:.4} (95%), exact {:.4}, universe ratio {:.4}",
+            player + 1,
+            probability,
+            margin,
+            exact,
+            ratio
+        );
+    }
+}
+
+fn test_simulation(positions: &HashMap<String, Vec<usize>>, rules: &HashMap<String, Rules>) {
+    let dirac = rules.get("dirac rules").unwrap();
+    let test = positions.get("test").unwrap();
+    let wins = simulate(test, dirac, 10000, 21);
+    assert_eq!(wins, simulate(test, dirac, 10000, 21));
+    assert_eq!(wins.iter().sum::<usize>(), 10000);
+    // 4 standard errors, so fixed seed is not a lucky one
+    let estimates = estimate_win_probabilities(&wins, 4.0);
+    let exact = exact_win_probabilities(test, dirac);
+    let ratios = universe_win_ratios(test, dirac);
+    assert!((exact.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    for (((probability, margin), exact), ratio) in estimates.iter().zip(exact).zip(ratios) {
+        assert!((probability - exact).abs() <= *margin);
+        assert!((probability - ratio).abs() > *margin);
+    }
+
+    assert_eq!(
+        estimate_win_probabilities(&[3, 1], 2.0),
+        vec![
+            (0.75, 2.0 * (0.75_f64 * 0.25 / 4.0).sqrt()),
+            (0.25, 2.0 * (0.75_f64 * 0.25 / 4.0).sqrt())
+        ]
+    );
+}
+
+// what wins of players are measured in: universes, or probability when every roll is equally likely
+trait Weight: Copy + Default + AddAssign + Mul<Output = Self> {
+    // weight of dice sum that happens in count universes out of total
+    fn of_roll(count: usize, total: usize) -> Self;
+}
+
+impl Weight for usize {
+    fn of_roll(count: usize, _total: usize) -> usize {
+        count
+    }
+}
+
+impl Weight for f64 {
+    fn of_roll(count: usize, total: usize) -> f64 {
+        count as f64 / total as f64
+    }
+}
+
+type WinsMemo<W> = HashMap<GameState, Vec<W>>;
+
+// wins of each player starting from given state
+fn solve_wins<W: Weight>(
+    state: &GameState,
+    all_rolls: &HashMap<usize, usize>,
+    rules: &Rules,
+    memo: &mut WinsMemo<W>,
+) -> Vec<W> {
+    if let Some(wins) = memo.get(state) {
+        return wins.clone();
+    }
+    let total_rolls = all_rolls.values().sum::<usize>();
+    let mut wins = vec![W::default(); state.len()];
+    for (dice_sum, dice_sum_count) in all_rolls.iter() {
+        let weight = W::of_roll(*dice_sum_count, total_rolls);
+        let next_state = make_next_state(state, *dice_sum, rules);
+        if let Some(winning_player) = get_winning_player(&next_state, rules) {
+            wins[winning_player] += weight;
+        } else {
+            let next_wins = solve_wins(&next_state, all_rolls, rules, memo);
+            for (win, next_win) in wins.iter_mut().zip(next_wins) {
+                *win += next_win * weight;
+            }
+        }
+    }
+    memo.insert(state.clone(), wins.clone());
+    wins
+}
+
+fn play_game_quantum_dice_memo(positions: &[usize], rules: &Rules) -> Vec<usize> {
+    let all_rolls = make_all_dice_rolls(rules);
+    solve_wins(
+        &make_start_state(positions),
+        &all_rolls,
+        rules,
+        &mut HashMap::new(),
+    )
+}
+
+// universes won by both players and probability of player 1 winning with a real dice
+struct WinTableEntry {
+    wins: [usize; 2],
+    probability: f64,
+}
+
+// entry for every pair of starting positions, table[pos1 - 1][pos2 - 1]
+fn make_win_table(rules: &Rules) -> Vec<Vec<WinTableEntry>> {
+    let all_rolls = make_all_dice_rolls(rules);
+    // states do not depend on starting positions, so memos are shared by all games
+    let mut wins_memo = HashMap::new();
+    let mut probabilities_memo = HashMap::new();
+    (1..=rules.board_size)
+        .map(|pos1| {
+            (1..=rules.board_size)
+                .map(|pos2| {
+                    let state = make_start_state(&[pos1, pos2]);
+                    let wins: Vec<usize> = solve_wins(&state, &all_rolls, rules, &mut wins_memo);
+                    let probabilities: Vec<f64> =
+                        solve_wins(&state, &all_rolls, rules, &mut probabilities_memo);
+                    WinTableEntry {
+                        wins: [wins[0], wins[1]],
+                        probability: probabilities[0],
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn print_win_table(table: &[Vec<WinTableEntry>]) {
+    println!(
+        "pos1 pos2 {:>16} {:>16} p1 win probability",
+        "p1 universes", "p2 universes"
+    );
+    for (pos1, row) in table.iter().enumerate() {
+        for (pos2, entry) in row.iter().enumerate() {
+            println!(
+                "{:>4} {:>4} {:>16} {:>16} {:.3}",
+                pos1 + 1,
+                pos2 + 1,
+                entry.wins[0],
+                entry.wins[1],
+                entry.probability
+            );
+        }
+    }
+    println!("player 1 win probability, rows are pos1, columns are pos2");
+    for row in table.iter() {
+        let probabilities = row
+            .iter()
+            .map(|entry| format!("{:.3}", entry.probability))
+            .collect::<Vec<_>>();
+        println!("{}", probabilities.join(" "));
+    }
+}
+
+fn test_win_table(positions: &HashMap<String, Vec<usize>>, rules: &HashMap<String, Rules>) {
+    let dirac = rules.get("dirac rules").unwrap();
+    let test = positions.get("test").unwrap();
+    assert_eq!(
+        play_game_quantum_dice(test, dirac),
+        play_game_quantum_dice_memo(test, dirac)
+    );
+    let three = positions.get("three players").unwrap();
+    let three_rules = rules.get("three players rules").unwrap();
+    assert_eq!(
+        play_game_quantum_dice(three, three_rules),
+        play_game_quantum_dice_memo(three, three_rules)
+    );
+
+    let table = make_win_table(dirac);
+    assert_eq!(table[3][7].wins, [444356092776315, 341960390180808]);
+    // table agrees with the frontier engine, checked on a few pairs
+    for [pos1, pos2] in [[1, 1], [10, 3]] {
+        let entry = &table[pos1 - 1][pos2 - 1];
+        assert_eq!(
+            entry.wins.to_vec(),
+            play_game_quantum_dice(&[pos1, pos2], dirac)
+        );
+        let probabilities = exact_win_probabilities(&[pos1, pos2], dirac);
+        assert!((entry.probability - probabilities[0]).abs() < 1e-12);
+    }
+    // probability is not the share of universes won
+    assert!((table[6][7].probability - 0.6604).abs() < 1e-4);
+    let [wins1, wins2] = table[6][7].wins;
+    assert!((wins1 as f64 / (wins1 + wins2) as f64 - 0.5782).abs() < 1e-4);
+}
+
+fn parse_positions(lines: &[&str]) -> Vec<usize> {
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (_, pos) = line.split_once(": ").unwrap();
+            pos.trim().parse().unwrap()
+        })
+        .collect()
+}
+
+fn parse_rules(lines: &[&str]) -> Rules {
+    let mut values = HashMap::new();
+    for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+        let (key, value) = line.trim().rsplit_once(' ').unwrap();
+        values.insert(key, value.parse::<usize>().unwrap());
+    }
+    let get = |key| *values.get(key).unwrap_or_else(|| panic!("no {}", key));
+    Rules {
+        rolls: get("rolls"),
+        dice_sides: get("dice sides"),
+        board_size: get("board size"),
+        max_score: get("max score"),
+    }
+}
+
+// sections with name ending with "rules" are rules, other sections are starting positions
+fn parse_input(s: &str) -> (HashMap<String, Vec<usize>>, HashMap<String, Rules>) {
+    let mut positions = HashMap::new();
+    let mut rules = HashMap::new();
+    for s in s.split("\n\n") {
+        let lines: Vec<_> = s.split('\n').collect();
+        let name = lines[0].to_string();
+        if name.ends_with("rules") {
+            rules.insert(name, parse_rules(&lines[1..]));
+        } else {
+            positions.insert(name, parse_positions(&lines[1..]));
+        }
+    }
+    (positions, rules)
+}
+
+fn test_players(rules: &HashMap<String, Rules>) {
+    let three = [4, 8, 7];
+    let quick = Rules {
+        max_score: 1,
+        ..*rules.get("dirac rules").unwrap()
     };
-    let rules1 = Rules {
-        board_size: 10,
-        dice_sides: 3,
-        max_score: 21,
-        rolls: 3,
+    // every position scores at least 1, so the first player always wins right away
+    assert_eq!(vec![27, 0, 0], play_game_quantum_dice(&three, &quick));
+
+    // one sided dice makes quantum game determenistic
+    let one_sided = Rules {
+        dice_sides: 1,
+        ..*rules.get("dirac rules").unwrap()
     };
-    assert_eq!(739785, play_game_determenistic_dice(4, 8, &rules0));
-    assert_eq!([444356092776315, 341960390180808], play_game_quantum_dice(4, 8, &rules1));
+    let finished = play_game(&three, &one_sided, &mut DeterministicDice::default());
+    let winning_player = get_winning_player(finished.keys().next().unwrap(), &one_sided).unwrap();
+    // single game loop ends in the same state as the shared engine
+    for (positions, rules) in [
+        (&three[..], &one_sided),
+        (&[4, 8], rules.get("deterministic rules").unwrap()),
+    ] {
+        let finished = play_game(positions, rules, &mut DeterministicDice::default());
+        let mut dice = DeterministicDice::default();
+        let (state, winner) = play_single_game(positions, rules, || dice.roll_sum(rules));
+        assert_eq!(Some(winner), get_winning_player(&state, rules));
+        assert_eq!(finished.into_iter().collect::<Vec<_>>(), vec![(state, 1)]);
+    }
+    let mut expected = vec![0; 3];
+    expected[winning_player] = 1;
+    assert_eq!(expected, play_game_quantum_dice(&three, &one_sided));
+    let mut dice = RandomDice::new(1);
+    assert_eq!(
+        winning_player,
+        play_game_random_dice(&three, &one_sided, &mut dice)
+    );
+    assert_eq!(0, play_game_random_dice(&three, &quick, &mut dice));
+    let rolls = dice.roll(&one_sided);
+    assert_eq!(rolls, vec![(3, 1)]);
+    let dirac = rules.get("dirac rules").unwrap();
+    for _ in 0..100 {
+        let (dice_sum, _) = dice.roll(dirac)[0];
+        assert!((3..=9).contains(&dice_sum));
+    }
+}
+
+pub fn main() {
+    let (positions, rules) = parse_input(&std::fs::read_to_string("input/day21.txt").unwrap());
+    let rules0 = rules.get("deterministic rules").unwrap();
+    let rules1 = rules.get("dirac rules").unwrap();
+    assert_eq!(
+        rules0,
+        &Rules {
+            board_size: 10,
+            dice_sides: 100,
+            max_score: 1000,
+            rolls: 3,
+        }
+    );
+    let test = positions.get("test").unwrap();
+    assert_eq!(739785, play_game_determenistic_dice(test, rules0));
+    assert_eq!(
+        vec![444356092776315, 341960390180808],
+        play_game_quantum_dice(test, rules1)
+    );
+    test_players(&rules);
+    test_win_table(&positions, &rules);
+    test_simulation(&positions, &rules);
     println!("tests ok");
-    println!("day 21 pt1 {:?}", play_game_determenistic_dice(7, 8, &rules0));
-    println!("day 21 pt2 {:?}", play_game_quantum_dice(7, 8, &rules1).iter().max().unwrap());
+    let day21 = positions.get("day21").unwrap();
+    println!(
+        "day 21 pt1 {:?}",
+        play_game_determenistic_dice(day21, rules0)
+    );
+    println!(
+        "day 21 pt2 {:?}",
+        play_game_quantum_dice(day21, rules1).iter().max().unwrap()
+    );
+    let three = positions.get("three players").unwrap();
+    println!(
+        "day 21 three players {:?} {:?}",
+        play_game_determenistic_dice(three, rules0),
+        play_game_quantum_dice_memo(three, rules.get("three players rules").unwrap())
+    );
+    print_win_table(&make_win_table(rules1));
+    print_simulation(day21, rules1, 100000, 21);
+    print_simulation(three, rules.get("three players rules").unwrap(), 100000, 21);
 }
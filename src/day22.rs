@@ -1,46 +1,79 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::Path;
 
 use regex::Regex;
 
-#[derive(Debug, Clone)]
-struct CubeCommand {
-    on: bool,
-    x: [isize; 2],
-    y: [isize; 2],
-    z: [isize; 2],
-}
+type CubeCommand = CommandN<3>;
 
 // world consists on cubes, each cube can be on or off
 // cubes are formed by axis aligned planes
-struct World {
-    x: Vec<isize>, // sorted list of yz planes coordinates
-    y: Vec<isize>,
-    z: Vec<isize>,
-    state: Vec<Vec<Vec<bool>>>, // state[iz][iy][ix] state of cube {x[ix], y[iy], z[iz]}-{x[ix+1], y[iy+1], z[iz+1]}
+struct World<const N: usize = 3> {
+    coords: [Vec<isize>; N], // sorted list of plane coordinates for every axis
+    state: Vec<bool>, // state[cell_index(c)] state of cube between planes c[a] and c[a] + 1 on every axis a
+}
+
+impl<const N: usize> World<N> {
+    // number of cubes along every axis
+    fn size(&self) -> [usize; N] {
+        std::array::from_fn(|axis| self.coords[axis].len().saturating_sub(1))
+    }
+
+    fn cell_index(&self, cell: [usize; N]) -> usize {
+        let size = self.size();
+        (0..N)
+            .rev()
+            .fold(0, |index, axis| index * size[axis] + cell[axis])
+    }
 }
 
-fn prepare_world(cmds: &[CubeCommand]) -> World {
-    let mut x = Vec::new();
-    let mut y = Vec::new();
-    let mut z = Vec::new();
+// every cell with from[a] <= c[a] < to[a] on every axis, first axis changes fastest
+fn cells<const N: usize>(from: [usize; N], to: [usize; N]) -> impl Iterator<Item = [usize; N]> {
+    let mut next = if (0..N).all(|axis| from[axis] < to[axis]) {
+        Some(from)
+    } else {
+        None
+    };
+    std::iter::from_fn(move || {
+        let cell = next?;
+        next = None;
+        let mut n = cell;
+        for axis in 0..N {
+            n[axis] += 1;
+            if n[axis] < to[axis] {
+                next = Some(n);
+                break;
+            }
+            n[axis] = from[axis];
+        }
+        Some(cell)
+    })
+}
+
+fn prepare_world<const N: usize>(cmds: &[CommandN<N>]) -> World<N> {
+    let mut coords: [Vec<isize>; N] = std::array::from_fn(|_| Vec::new());
 
     for cmd in cmds.iter() {
-        for (world_coords, cmd_borders) in [(&mut x, &cmd.x), (&mut y, &cmd.y), (&mut z, &cmd.z)] {
+        for (world_coords, cmd_borders) in coords.iter_mut().zip(cmd.cuboid.0.iter()) {
             world_coords.push(cmd_borders[0]);
             world_coords.push(cmd_borders[1] + 1);
         }
     }
 
-    for vec in [&mut x, &mut y, &mut z].into_iter() {
+    for vec in coords.iter_mut() {
         vec.sort();
         vec.dedup();
     }
 
-    let state = vec![vec![vec![false; x.len()]; y.len()]; z.len()];
-    World { x, y, z, state }
+    let mut world = World {
+        coords,
+        state: Vec::new(),
+    };
+    world.state = vec![false; world.size().iter().product()];
+    world
 }
 
-fn apply_command(world: &mut World, cmd: &CubeCommand) {
+fn apply_command<const N: usize>(world: &mut World<N>, cmd: &CommandN<N>) {
     fn find_indices(world_coords: &[isize], cmd_borders: &[isize; 2]) -> (usize, usize) {
         (
             world_coords.binary_search(&cmd_borders[0]).unwrap(),
@@ -48,57 +81,145 @@ fn apply_command(world: &mut World, cmd: &CubeCommand) {
         )
     }
 
-    let ix = find_indices(&world.x, &cmd.x);
-    let iy = find_indices(&world.y, &cmd.y);
-    let iz = find_indices(&world.z, &cmd.z);
-
-    for x in ix.0..ix.1 {
-        for y in iy.0..iy.1 {
-            for z in iz.0..iz.1 {
-                world.state[z][y][x] = cmd.on;
-            }
-        }
+    let indices: [(usize, usize); N] =
+        std::array::from_fn(|axis| find_indices(&world.coords[axis], &cmd.cuboid.0[axis]));
+    let from = indices.map(|i| i.0);
+    let to = indices.map(|i| i.1);
+    for cell in cells(from, to) {
+        let index = world.cell_index(cell);
+        world.state[index] = cmd.on;
     }
 }
 
-fn apply_commands(world: &mut World, cmds: &[CubeCommand]) {
+fn apply_commands<const N: usize>(world: &mut World<N>, cmds: &[CommandN<N>]) {
     for cmd in cmds.iter() {
         apply_command(world, cmd);
     }
 }
 
-fn make_world(cmds: &[CubeCommand]) -> World {
+fn make_world<const N: usize>(cmds: &[CommandN<N>]) -> World<N> {
     let mut world = prepare_world(cmds);
     apply_commands(&mut world, cmds);
     world
 }
 
-fn count_on(world: &World) -> isize {
+fn count_on<const N: usize>(world: &World<N>) -> isize {
     let mut total_vol = 0;
-    for iz in 0..world.z.len() - 1 {
-        let z = [world.z[iz], world.z[iz + 1]];
-        let dz = z[1] - z[0];
-        for iy in 0..world.y.len() - 1 {
-            let y = [world.y[iy], world.y[iy + 1]];
-            let dy = y[1] - y[0];
-            for ix in 0..world.x.len() - 1 {
-                let x = [world.x[ix], world.x[ix + 1]];
-                let dx = x[1] - x[0];
-                let on = world.state[iz][iy][ix];
-                if on {
-                    let vol = dx * dy * dz;
-                    total_vol += vol;
+    for cell in cells([0; N], world.size()) {
+        if world.state[world.cell_index(cell)] {
+            let vol = (0..N)
+                .map(|axis| world.coords[axis][cell[axis] + 1] - world.coords[axis][cell[axis]])
+                .product::<isize>();
+            total_vol += vol;
+        }
+    }
+    total_vol
+}
+
+// lit cells of world merged greedily into boxes: run along x, then rows along y, then layers along z
+fn lit_boxes(world: &World) -> Vec<Cuboid> {
+    let [nx, ny, nz] = world.size();
+    let mut covered = vec![false; world.state.len()];
+    let free = |covered: &Vec<bool>, x: usize, y: usize, z: usize| {
+        let index = world.cell_index([x, y, z]);
+        world.state[index] && !covered[index]
+    };
+    let mut boxes = Vec::new();
+    for z0 in 0..nz {
+        for y0 in 0..ny {
+            for x0 in 0..nx {
+                if !free(&covered, x0, y0, z0) {
+                    continue;
+                }
+                let mut x1 = x0 + 1;
+                while x1 < nx && free(&covered, x1, y0, z0) {
+                    x1 += 1;
+                }
+                let mut y1 = y0 + 1;
+                while y1 < ny && (x0..x1).all(|x| free(&covered, x, y1, z0)) {
+                    y1 += 1;
+                }
+                let mut z1 = z0 + 1;
+                while z1 < nz && (y0..y1).all(|y| (x0..x1).all(|x| free(&covered, x, y, z1))) {
+                    z1 += 1;
+                }
+                for cell in cells([x0, y0, z0], [x1, y1, z1]) {
+                    covered[world.cell_index(cell)] = true;
                 }
+                let [x, y, z] = &world.coords;
+                boxes.push(Cuboid([
+                    [x[x0], x[x1] - 1],
+                    [y[y0], y[y1] - 1],
+                    [z[z0], z[z1] - 1],
+                ]));
             }
         }
     }
-    total_vol
+    boxes
+}
+
+// writes boxes as Wavefront OBJ, every box is 8 vertices and 6 quads facing outwards,
+// cube [a, b] occupies space from a to b + 1; returns number of written boxes
+fn write_obj(boxes: &[Cuboid], clip: Option<&Cuboid>, out: &mut dyn Write) -> io::Result<usize> {
+    const FACES: [[usize; 4]; 6] = [
+        [0, 1, 3, 2], // x min
+        [4, 6, 7, 5], // x max
+        [0, 4, 5, 1], // y min
+        [2, 3, 7, 6], // y max
+        [0, 2, 6, 4], // z min
+        [1, 5, 7, 3], // z max
+    ];
+    writeln!(
+        out,
+        "# day22 reactor, {} boxes before clipping",
+        boxes.len()
+    )?;
+    let mut count = 0;
+    for b in boxes {
+        let b = match clip {
+            Some(region) => match b.intersect(region) {
+                Some(b) => b,
+                None => continue,
+            },
+            None => *b,
+        };
+        // vertex i has x max if bit 2 is set, y max for bit 1, z max for bit 0
+        for i in 0..8 {
+            let corner = |axis: usize, bit: usize| {
+                if i & bit == 0 {
+                    b.0[axis][0]
+                } else {
+                    b.0[axis][1] + 1
+                }
+            };
+            writeln!(out, "v {} {} {}", corner(0, 4), corner(1, 2), corner(2, 1))?;
+        }
+        for face in FACES {
+            let base = count * 8 + 1;
+            writeln!(
+                out,
+                "f {} {} {} {}",
+                base + face[0],
+                base + face[1],
+                base + face[2],
+                base + face[3]
+            )?;
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn export_obj(world: &World, clip: Option<&Cuboid>, filename: &Path) -> io::Result<usize> {
+    let mut out = io::BufWriter::new(std::fs::File::create(filename)?);
+    write_obj(&lit_boxes(world), clip, &mut out)
 }
 
 fn filter_commands(cmds: &[CubeCommand], size: isize) -> Vec<CubeCommand> {
     cmds.iter()
         .filter(|cmd| {
-            ![cmd.x, cmd.y, cmd.z]
+            !cmd.cuboid
+                .0
                 .iter()
                 .flatten()
                 .any(|&c| c < -size || c > size)
@@ -107,47 +228,580 @@ fn filter_commands(cmds: &[CubeCommand], size: isize) -> Vec<CubeCommand> {
         .collect()
 }
 
-fn solve_pt1(input: &[CubeCommand]) -> isize {
-    let world = make_world(&filter_commands(input, 50));
-    count_on(&world)
+// box of cubes in N dimensions, borders are inclusive as in commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Cuboid<const N: usize = 3>([[isize; 2]; N]);
+
+impl<const N: usize> Cuboid<N> {
+    fn contains(&self, point: [isize; N]) -> bool {
+        self.0
+            .iter()
+            .zip(point)
+            .all(|([from, to], p)| *from <= p && p <= *to)
+    }
+
+    fn volume(&self) -> isize {
+        self.0.iter().map(|[from, to]| to - from + 1).product()
+    }
+
+    fn intersect(&self, other: &Cuboid<N>) -> Option<Cuboid<N>> {
+        let mut result = *self;
+        for (r, o) in result.0.iter_mut().zip(other.0.iter()) {
+            *r = [r[0].max(o[0]), r[1].min(o[1])];
+            if r[0] > r[1] {
+                return None;
+            }
+        }
+        Some(result)
+    }
+
+    // parts of self that are not in other, parts don't overlap
+    fn subtract(&self, other: &Cuboid<N>) -> Vec<Cuboid<N>> {
+        let inter = match self.intersect(other) {
+            Some(inter) => inter,
+            None => return vec![*self],
+        };
+        let mut parts = Vec::new();
+        // cut slabs before and after intersection on every axis, rest shrinks to intersection
+        let mut rest = *self;
+        for axis in 0..N {
+            if rest.0[axis][0] < inter.0[axis][0] {
+                let mut part = rest;
+                part.0[axis][1] = inter.0[axis][0] - 1;
+                parts.push(part);
+                rest.0[axis][0] = inter.0[axis][0];
+            }
+            if rest.0[axis][1] > inter.0[axis][1] {
+                let mut part = rest;
+                part.0[axis][0] = inter.0[axis][1] + 1;
+                parts.push(part);
+                rest.0[axis][1] = inter.0[axis][1];
+            }
+        }
+        parts
+    }
 }
 
-fn solve_pt2(input: &[CubeCommand]) -> isize {
-    let world = make_world(input);
-    count_on(&world)
+// lit cubes as list of non overlapping cuboids, memory grows with number of cuboids
+// instead of cube of number of distinct coordinates
+#[derive(Default)]
+struct Reactor<const N: usize = 3> {
+    cuboids: Vec<Cuboid<N>>,
+}
+
+impl<const N: usize> Reactor<N> {
+    fn apply_command(&mut self, cmd: &CommandN<N>) {
+        self.apply(cmd.on, cmd.cuboid);
+    }
+
+    fn apply(&mut self, on: bool, cuboid: Cuboid<N>) {
+        self.cuboids = self
+            .cuboids
+            .iter()
+            .flat_map(|c| c.subtract(&cuboid))
+            .collect();
+        if on {
+            self.cuboids.push(cuboid);
+        }
+    }
+
+    fn count_on(&self) -> isize {
+        self.cuboids.iter().map(|c| c.volume()).sum()
+    }
+
+    // lit cubes inside of region, cuboids sticking out of region are clipped
+    fn count_on_in(&self, region: &Cuboid<N>) -> isize {
+        self.cuboids
+            .iter()
+            .filter_map(|c| c.intersect(region))
+            .map(|c| c.volume())
+            .sum()
+    }
+
+    fn is_on(&self, point: [isize; N]) -> bool {
+        self.cuboids.iter().any(|c| c.contains(point))
+    }
+}
+
+fn make_reactor<const N: usize>(cmds: &[CommandN<N>]) -> Reactor<N> {
+    let mut reactor = Reactor::default();
+    for cmd in cmds.iter() {
+        reactor.apply_command(cmd);
+    }
+    reactor
+}
+
+const AXES: [&str; 4] = ["x", "y", "z", "w"];
+
+// x, y, z, w for up to 4 dimensions, x0, x1, ... for more
+fn axis_name(axis: usize, dimensions: usize) -> String {
+    if dimensions <= AXES.len() {
+        AXES[axis].to_string()
+    } else {
+        format!("x{}", axis)
+    }
+}
+
+// command in any number of dimensions
+#[derive(Debug, Clone, PartialEq)]
+struct CommandN<const N: usize> {
+    on: bool,
+    cuboid: Cuboid<N>,
+}
+
+// parser of commands in N dimensions, regex is built once and reused for every line
+struct CommandParser<const N: usize> {
+    re: Regex,
+}
+
+impl<const N: usize> CommandParser<N> {
+    fn new() -> CommandParser<N> {
+        let ranges = (0..N)
+            .map(|axis| format!(r"{}=(-?\d+)\.\.(-?\d+)", axis_name(axis, N)))
+            .collect::<Vec<_>>();
+        CommandParser {
+            re: Regex::new(&format!("^(on|off) {}$", ranges.join(","))).unwrap(),
+        }
+    }
+
+    // "on x=1..2,y=3..4" for N = 2, axes have to be given in order
+    fn parse(&self, s: &str) -> Result<CommandN<N>, String> {
+        let cap = self
+            .re
+            .captures(s)
+            .ok_or(format!("{} does not match {}", s, self.re))?;
+        let mut cuboid = Cuboid([[0; 2]; N]);
+        for (axis, range) in cuboid.0.iter_mut().enumerate() {
+            for (i, border) in range.iter_mut().enumerate() {
+                let value = cap.get(2 + axis * 2 + i).unwrap().as_str();
+                *border = value.parse().map_err(|e| format!("{}: {}", value, e))?;
+            }
+            if range[0] > range[1] {
+                return Err(format!(
+                    "{}: {} range {}..{} is reversed",
+                    s,
+                    axis_name(axis, N),
+                    range[0],
+                    range[1]
+                ));
+            }
+        }
+        Ok(CommandN {
+            on: cap.get(1).unwrap().as_str() == "on",
+            cuboid,
+        })
+    }
+}
+
+// applied command with everything needed to undo it
+struct Step {
+    cmd: CubeCommand,
+    removed: Vec<Cuboid>, // lit cuboids that intersected command before it
+    added: Vec<Cuboid>,   // parts of removed cuboids left after command and command itself if on
+    change: isize,        // change of lit volume
+}
+
+// reactor that applies commands one by one and keeps history of them
+#[derive(Default)]
+struct IncrementalReactor {
+    reactor: Reactor,
+    steps: Vec<Step>,
+    lit: isize,
+}
+
+impl IncrementalReactor {
+    // returns change of lit volume
+    fn apply_command(&mut self, cmd: &CubeCommand) -> isize {
+        let cuboid = cmd.cuboid;
+        let (removed, untouched): (Vec<Cuboid>, Vec<Cuboid>) = self
+            .reactor
+            .cuboids
+            .iter()
+            .partition(|c| c.intersect(&cuboid).is_some());
+        let mut added = removed
+            .iter()
+            .flat_map(|c| c.subtract(&cuboid))
+            .collect::<Vec<_>>();
+        if cmd.on {
+            added.push(cuboid);
+        }
+        let volume = |cuboids: &[Cuboid]| cuboids.iter().map(|c| c.volume()).sum::<isize>();
+        let change = volume(&added) - volume(&removed);
+        self.reactor.cuboids = untouched;
+        self.reactor.cuboids.extend(added.iter().cloned());
+        self.lit += change;
+        self.steps.push(Step {
+            cmd: cmd.clone(),
+            removed,
+            added,
+            change,
+        });
+        change
+    }
+
+    // reverts last command and returns it
+    fn undo(&mut self) -> Option<CubeCommand> {
+        let step = self.steps.pop()?;
+        let added = step.added.iter().collect::<HashSet<_>>();
+        self.reactor.cuboids.retain(|c| !added.contains(c));
+        self.reactor.cuboids.extend(step.removed);
+        self.lit -= step.change;
+        Some(step.cmd)
+    }
+
+    // number of cubes that command i switched on or off
+    fn toggled(&self, i: usize) -> isize {
+        self.steps[i].change.abs()
+    }
+
+    // lit volume after every step
+    fn volume_history(&self) -> Vec<isize> {
+        self.steps
+            .iter()
+            .scan(0, |lit, step| {
+                *lit += step.change;
+                Some(*lit)
+            })
+            .collect()
+    }
+
+    // indices of n commands that toggled most cubes
+    fn dominant_steps(&self, n: usize) -> Vec<usize> {
+        let mut indices = (0..self.steps.len()).collect::<Vec<_>>();
+        indices.sort_by_key(|&i| std::cmp::Reverse(self.toggled(i)));
+        indices.truncate(n);
+        indices
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Engine {
+    Grid,        // dense grid over compressed coordinates
+    Subtraction, // list of non overlapping cuboids
+}
+
+fn count_with_engine<const N: usize>(cmds: &[CommandN<N>], engine: Engine) -> isize {
+    match engine {
+        Engine::Grid => count_on(&make_world(cmds)),
+        Engine::Subtraction => make_reactor(cmds).count_on(),
+    }
+}
+
+fn solve_pt1(input: &[CubeCommand], engine: Engine) -> isize {
+    count_with_engine(&filter_commands(input, 50), engine)
+}
+
+fn solve_pt2(input: &[CubeCommand], engine: Engine) -> isize {
+    count_with_engine(input, engine)
+}
+
+fn test_cuboid() {
+    let a = Cuboid([[0, 9], [0, 9], [0, 9]]);
+    let b = Cuboid([[5, 14], [-5, 4], [3, 3]]);
+    assert_eq!(a.intersect(&b), Some(Cuboid([[5, 9], [0, 4], [3, 3]])));
+    let parts = a.subtract(&b);
+    assert_eq!(parts.len(), 4);
+    assert_eq!(parts.iter().map(|c| c.volume()).sum::<isize>(), 1000 - 25);
+    for (i, p) in parts.iter().enumerate() {
+        assert_eq!(p.intersect(&b), None);
+        assert!(parts[i + 1..].iter().all(|q| p.intersect(q).is_none()));
+    }
+    // inner cuboid cuts outer one into 6 parts
+    let inner = Cuboid([[1, 8], [1, 8], [1, 8]]);
+    assert_eq!(a.subtract(&inner).len(), 6);
+    assert_eq!(inner.subtract(&a), vec![]);
+    assert_eq!(a.subtract(&Cuboid([[10, 10], [0, 0], [0, 0]])), vec![a]);
+}
+
+fn test_queries(input: &HashMap<String, Vec<CubeCommand>>) {
+    let reactor = make_reactor(input.get("small").unwrap());
+    assert_eq!(reactor.count_on(), 39);
+    assert!(reactor.is_on([10, 10, 10]));
+    assert!(!reactor.is_on([11, 11, 11]));
+    assert!(reactor.is_on([13, 13, 13]));
+    assert!(!reactor.is_on([9, 9, 9]));
+    // region count agrees with point queries
+    let region = Cuboid([[11, 14], [9, 12], [10, 13]]);
+    let mut count = 0;
+    for x in region.0[0][0]..=region.0[0][1] {
+        for y in region.0[1][0]..=region.0[1][1] {
+            for z in region.0[2][0]..=region.0[2][1] {
+                count += reactor.is_on([x, y, z]) as isize;
+            }
+        }
+    }
+    assert_eq!(reactor.count_on_in(&region), count);
+
+    // part 1 is count in -50..50 region, large commands in inputs are entirely outside of it
+    let init_region = Cuboid([[-50, 50]; 3]);
+    for name in ["test", "test2", "day22"] {
+        let cmds = input.get(name).unwrap();
+        assert_eq!(
+            make_reactor(cmds).count_on_in(&init_region),
+            solve_pt1(cmds, Engine::Subtraction)
+        );
+    }
+    // clipping counts parts of commands that stick out, filtering ignores them
+    let cmds = parse_input("clip\non x=40..60,y=0..0,z=0..0");
+    let cmds = cmds.get("clip").unwrap();
+    assert_eq!(make_reactor(cmds).count_on_in(&init_region), 11);
+    assert_eq!(solve_pt1(cmds, Engine::Subtraction), 0);
+}
+
+fn test_export(input: &HashMap<String, Vec<CubeCommand>>) {
+    // world without commands has no boxes
+    assert!(lit_boxes(&make_world(&[])).is_empty());
+    let small = input.get("small").unwrap();
+    assert!(lit_boxes(&make_world(&filter_commands(small, 5))).is_empty());
+
+    let world = make_world(small);
+    let boxes = lit_boxes(&world);
+    assert_eq!(boxes.iter().map(|b| b.volume()).sum::<isize>(), 39);
+    for (i, b) in boxes.iter().enumerate() {
+        assert!(boxes[i + 1..].iter().all(|c| b.intersect(c).is_none()));
+    }
+    assert!(boxes.len() < 39);
+
+    let mut out = Vec::new();
+    let count = write_obj(&boxes, None, &mut out).unwrap();
+    let obj = String::from_utf8(out).unwrap();
+    assert_eq!(count, boxes.len());
+    assert_eq!(
+        obj.lines().filter(|l| l.starts_with("v ")).count(),
+        8 * count
+    );
+    assert_eq!(
+        obj.lines().filter(|l| l.starts_with("f ")).count(),
+        6 * count
+    );
+    assert!(obj.contains("\nv 10 10 10\n"));
+
+    // only cubes with x = 13 are left
+    let clip = Cuboid([[13, 100], [0, 100], [0, 100]]);
+    let mut out = Vec::new();
+    let count = write_obj(&boxes, Some(&clip), &mut out).unwrap();
+    let clipped = boxes
+        .iter()
+        .filter_map(|b| b.intersect(&clip))
+        .collect::<Vec<_>>();
+    assert_eq!(count, clipped.len());
+    assert_eq!(clipped.iter().map(|b| b.volume()).sum::<isize>(), 9);
+    let obj = String::from_utf8(out).unwrap();
+    assert!(obj.lines().filter(|l| l.starts_with("v ")).all(|l| l
+        .split(' ')
+        .nth(1)
+        .unwrap()
+        .parse::<isize>()
+        .unwrap()
+        >= 13));
+}
+
+fn test_incremental(input: &HashMap<String, Vec<CubeCommand>>) {
+    let cmds = input.get("test").unwrap();
+    let mut reactor = IncrementalReactor::default();
+    for (i, cmd) in cmds.iter().enumerate() {
+        let change = reactor.apply_command(cmd);
+        assert_eq!(change.abs(), reactor.toggled(i));
+        assert_eq!(reactor.lit, make_reactor(&cmds[..=i]).count_on());
+        assert_eq!(reactor.lit, reactor.reactor.count_on());
+    }
+    let history = reactor.volume_history();
+    assert_eq!(history.len(), cmds.len());
+    assert_eq!(*history.last().unwrap(), reactor.lit);
+    // off command that hits nothing toggles nothing
+    let off = parse_input("off\noff x=1000..1001,y=0..0,z=0..0");
+    assert_eq!(reactor.apply_command(&off.get("off").unwrap()[0]), 0);
+    assert!(reactor.undo().is_some());
+
+    // undo goes back through all the same volumes
+    for i in (0..cmds.len()).rev() {
+        assert_eq!(reactor.lit, history[i]);
+        assert_eq!(reactor.reactor.count_on(), history[i]);
+        assert_eq!(reactor.undo().unwrap(), cmds[i]);
+    }
+    assert_eq!(reactor.lit, 0);
+    assert!(reactor.reactor.cuboids.is_empty());
+    assert!(reactor.undo().is_none());
+
+    let cmds = input.get("test2").unwrap();
+    for cmd in cmds.iter() {
+        reactor.apply_command(cmd);
+    }
+    assert_eq!(reactor.lit, 2758514936282235);
+    let top = reactor.dominant_steps(3);
+    assert_eq!(top.len(), 3);
+    assert!(reactor.toggled(top[0]) >= reactor.toggled(top[1]));
+    assert!(reactor.toggled(top[1]) >= reactor.toggled(top[2]));
+}
+
+fn test_dimensions() {
+    let source = std::fs::read_to_string("input/day22.txt").unwrap();
+    let test2 = source
+        .split("\n\n")
+        .find(|s| s.starts_with("test2"))
+        .unwrap();
+    let lines = test2.lines().skip(1).collect::<Vec<_>>();
+    let parser2 = CommandParser::<2>::new();
+    let parser3 = CommandParser::<3>::new();
+    let parser4 = CommandParser::<4>::new();
+    let parser5 = CommandParser::<5>::new();
+    let cmds3 = lines
+        .iter()
+        .map(|line| parser3.parse(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        count_with_engine(&cmds3, Engine::Subtraction),
+        2758514936282235
+    );
+    assert!(parser2.parse(lines[0]).is_err());
+    assert!(parser4.parse(lines[0]).is_err());
+    assert!(parser5.parse(lines[0]).is_err());
+
+    // axes have to be named and ordered, ranges go up
+    assert!(parser2.parse("on x=0..1,y=0..1").is_ok());
+    assert!(parser2.parse("on y=0..1,x=0..1").is_err());
+    assert!(parser2.parse("on x=0..1,z=0..1").is_err());
+    assert!(parser2.parse("toggle x=0..1,y=0..1").is_err());
+    assert_eq!(
+        parser2.parse("on x=0..1,y=1..0"),
+        Err("on x=0..1,y=1..0: y range 1..0 is reversed".to_string())
+    );
+
+    // 4D commands with one layer of w have the same volume, two layers double it
+    for (w, factor) in [("0..0", 1), ("-5..-4", 2)] {
+        let cmds4 = lines
+            .iter()
+            .map(|line| parser4.parse(&format!("{},w={}", line, w)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            count_with_engine(&cmds4, Engine::Subtraction),
+            2758514936282235 * factor
+        );
+    }
+
+    // 2D rectangles are the same as 3D commands flattened to z = 0
+    let flat = cmds3
+        .iter()
+        .map(|cmd| CommandN {
+            on: cmd.on,
+            cuboid: Cuboid([cmd.cuboid.0[0], cmd.cuboid.0[1], [0, 0]]),
+        })
+        .collect::<Vec<_>>();
+    let cmds2 = cmds3
+        .iter()
+        .map(|cmd| CommandN {
+            on: cmd.on,
+            cuboid: Cuboid([cmd.cuboid.0[0], cmd.cuboid.0[1]]),
+        })
+        .collect::<Vec<_>>();
+    for engine in [Engine::Grid, Engine::Subtraction] {
+        assert_eq!(
+            count_with_engine(&cmds2, engine),
+            count_with_engine(&flat, Engine::Subtraction)
+        );
+    }
+
+    let squares = ["on x=0..1,y=0..1", "on x=1..2,y=1..2", "off x=0..0,y=2..2"]
+        .iter()
+        .map(|line| parser2.parse(line).unwrap())
+        .collect::<Vec<_>>();
+    for engine in [Engine::Grid, Engine::Subtraction] {
+        assert_eq!(count_with_engine(&squares, engine), 7);
+    }
+    let cubes4 = [
+        "on x=0..1,y=0..1,z=0..1,w=0..1",
+        "on x=1..2,y=1..2,z=1..2,w=1..2",
+    ]
+    .iter()
+    .map(|line| parser4.parse(line).unwrap())
+    .collect::<Vec<_>>();
+    assert_eq!(count_with_engine(&cubes4, Engine::Grid), 16 + 16 - 1);
+    let reactor = make_reactor(&cubes4);
+    assert_eq!(reactor.count_on(), 16 + 16 - 1);
+    assert!(reactor.is_on([2, 2, 2, 2]));
+    assert!(!reactor.is_on([0, 0, 0, 2]));
+
+    // more than 4 axes are numbered
+    let cubes5 = [
+        "on x0=0..1,x1=0..1,x2=0..1,x3=0..1,x4=0..1",
+        "on x0=1..2,x1=1..2,x2=1..2,x3=1..2,x4=1..2",
+        "off x0=0..0,x1=0..0,x2=0..0,x3=0..0,x4=-5..5",
+    ]
+    .iter()
+    .map(|line| parser5.parse(line).unwrap())
+    .collect::<Vec<_>>();
+    for engine in [Engine::Grid, Engine::Subtraction] {
+        assert_eq!(count_with_engine(&cubes5, engine), 32 + 32 - 1 - 2);
+    }
+    assert!(make_reactor(&cubes5).is_on([2, 2, 2, 2, 2]));
+    assert!(parser5
+        .parse("on x=0..1,y=0..1,z=0..1,w=0..1,v=0..1")
+        .is_err());
+    assert_eq!(
+        parser5.parse("on x0=0..1,x1=0..1,x2=0..1,x3=0..1,x4=1..0"),
+        Err("on x0=0..1,x1=0..1,x2=0..1,x3=0..1,x4=1..0: x4 range 1..0 is reversed".to_string())
+    );
 }
 
 pub fn main() {
     let input = parse_input(&std::fs::read_to_string("input/day22.txt").unwrap());
-    assert_eq!(590784, solve_pt1(input.get("test").unwrap()));
-    assert_eq!(474140, solve_pt1(input.get("test2").unwrap()));
-    assert_eq!(2758514936282235, solve_pt2(input.get("test2").unwrap()));
+    test_cuboid();
+    test_queries(&input);
+    test_export(&input);
+    test_incremental(&input);
+    test_dimensions();
+    for engine in [Engine::Grid, Engine::Subtraction] {
+        assert_eq!(590784, solve_pt1(input.get("test").unwrap(), engine));
+        assert_eq!(474140, solve_pt1(input.get("test2").unwrap(), engine));
+        assert_eq!(
+            2758514936282235,
+            solve_pt2(input.get("test2").unwrap(), engine)
+        );
+    }
+    let day22 = input.get("day22").unwrap();
+    assert_eq!(
+        solve_pt1(day22, Engine::Grid),
+        solve_pt1(day22, Engine::Subtraction)
+    );
+    assert_eq!(
+        solve_pt2(day22, Engine::Grid),
+        solve_pt2(day22, Engine::Subtraction)
+    );
     println!("tests ok");
-    println!("day 22 pt1 {}", solve_pt1(input.get("day22").unwrap()));
-    println!("day 22 pt2 {}", solve_pt2(input.get("day22").unwrap()));
+    println!("day 22 pt1 {}", solve_pt1(day22, Engine::Subtraction));
+    let filename = std::env::temp_dir().join("day22_pt1.obj");
+    let world = make_world(&filter_commands(day22, 50));
+    let count = export_obj(&world, Some(&Cuboid([[-50, 50]; 3])), &filename).unwrap();
+    println!(
+        "day 22 pt1 {} boxes written to {}",
+        count,
+        filename.display()
+    );
+    println!("day 22 pt2 {}", solve_pt2(day22, Engine::Subtraction));
+    let mut reactor = IncrementalReactor::default();
+    for cmd in day22.iter() {
+        reactor.apply_command(cmd);
+    }
+    for i in reactor.dominant_steps(3) {
+        println!(
+            "day 22 pt2 command {} toggled {} cubes, lit after it {}",
+            i,
+            reactor.toggled(i),
+            reactor.volume_history()[i]
+        );
+    }
 }
 
 fn parse_input(s: &str) -> HashMap<String, Vec<CubeCommand>> {
-    let re =
-        Regex::new(r"^(on|off) x=(-?\d+)..(-?\d+),y=(-?\d+)..(-?\d+),z=(-?\d+)..(-?\d+)$").unwrap();
     let mut result = HashMap::new();
+    let parser = CommandParser::new();
     s.split("\n\n").for_each(|s| {
         let lines: Vec<_> = s.split("\n").collect();
         let name = lines[0].to_string();
-        let mut commands = Vec::new();
-        for line in &lines[1..] {
-            let cap = re.captures(line).unwrap();
-            let r: Vec<_> = (2..=7)
-                .map(|i| cap.get(i).unwrap().as_str().parse::<isize>().unwrap())
-                .collect();
-            let cmd = CubeCommand {
-                on: cap.get(1).unwrap().as_str() == "on",
-                x: [r[0], r[1]],
-                y: [r[2], r[3]],
-                z: [r[4], r[5]],
-            };
-            commands.push(cmd);
-        }
+        let commands = lines[1..]
+            .iter()
+            .map(|line| parser.parse(line).unwrap())
+            .collect();
         result.insert(name, commands);
     });
     result
@@ -1,12 +1,78 @@
-use std::{collections::HashMap, hash::Hash, rc::Rc};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt,
+    hash::Hash,
+    io::{self, BufRead, Write},
+    rc::Rc,
+    time::Instant,
+};
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 struct State {
     hall: Vec<char>, // '.' - empty, 'A', 'B' - etc amphypod
     rooms: Vec<Vec<char>>,
     room_coords: Vec<usize>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Position {
+    Hall(usize),        // x in hall
+    Room(usize, usize), // room number and depth, 0 is next to the hall
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Position::Hall(x) => write!(f, "hall {}", x),
+            Position::Room(room_n, y) => write!(f, "room {} depth {}", room_n, y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Move {
+    apod: char,
+    from: Position,
+    to: Position,
+    cost: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} from {} to {}, energy {}",
+            self.apod, self.from, self.to, self.cost
+        )
+    }
+}
+
+impl State {
+    fn get(&self, pos: Position) -> char {
+        match pos {
+            Position::Hall(x) => self.hall[x],
+            Position::Room(room_n, y) => self.rooms[room_n][y],
+        }
+    }
+
+    fn set(&mut self, pos: Position, c: char) {
+        match pos {
+            Position::Hall(x) => self.hall[x] = c,
+            Position::Room(room_n, y) => self.rooms[room_n][y] = c,
+        }
+    }
+
+    fn apply_move(&self, m: &Move) -> State {
+        assert_eq!(self.get(m.from), m.apod);
+        assert_eq!(self.get(m.to), '.');
+        let mut new_state = self.clone();
+        new_state.set(m.from, '.');
+        new_state.set(m.to, m.apod);
+        new_state
+    }
+}
+
 fn is_apod_char(c: char) -> bool {
     return c.is_ascii_alphabetic() && c.is_ascii_uppercase();
 }
@@ -96,7 +162,7 @@ fn make_next_states(
     state: &State,
     move_cost: &HashMap<char, usize>,
     home_room: &HashMap<char, usize>,
-) -> Vec<(State, usize)> {
+) -> Vec<(State, Move)> {
     let mut next_states = Vec::new();
     // move every apod in hall to its home room
     for x in 0..state.hall.len() {
@@ -108,11 +174,13 @@ fn make_next_states(
                 let room_x = state.room_coords[room_n];
                 if let Some(y) = get_top_free_pos_in_room(room_n, state) {
                     if can_move_in_hall(state, x, room_x) {
-                        let cost = unit_cost * ((y + 1) + abs_diff(x, room_x));
-                        let mut new_state = (*state).clone();
-                        new_state.hall[x] = '.';
-                        new_state.rooms[room_n][y] = apod;
-                        next_states.push((new_state, cost));
+                        let m = Move {
+                            apod,
+                            from: Position::Hall(x),
+                            to: Position::Room(room_n, y),
+                            cost: unit_cost * ((y + 1) + abs_diff(x, room_x)),
+                        };
+                        next_states.push((state.apply_move(&m), m));
                     }
                 }
             }
@@ -125,11 +193,13 @@ fn make_next_states(
             if let Some((top_apod, top_apod_y)) = get_top_apod_in_room(room_n, state) {
                 let unit_cost = *move_cost.get(&top_apod).unwrap();
                 for x in find_possible_positions_in_hall(room_n, state) {
-                    let cost = unit_cost * ((top_apod_y + 1) + abs_diff(x, room_x));
-                    let mut new_state = (*state).clone();
-                    new_state.hall[x] = top_apod;
-                    new_state.rooms[room_n][top_apod_y] = '.';
-                    next_states.push((new_state, cost));
+                    let m = Move {
+                        apod: top_apod,
+                        from: Position::Room(room_n, top_apod_y),
+                        to: Position::Hall(x),
+                        cost: unit_cost * ((top_apod_y + 1) + abs_diff(x, room_x)),
+                    };
+                    next_states.push((state.apply_move(&m), m));
                 }
             }
         }
@@ -137,13 +207,29 @@ fn make_next_states(
     next_states
 }
 
+struct Solution {
+    cost: usize,
+    moves: Vec<Move>,
+}
+
 fn find_arrange_cost(
     state: &State,
     move_cost: &HashMap<char, usize>,
     home_room: &HashMap<char, usize>,
 ) -> usize {
+    find_arrangement(state, move_cost, home_room).cost
+}
+
+// lowest known cost of state and previous state with move that leads to the state
+type Visited = HashMap<Rc<State>, (usize, Option<(Rc<State>, Move)>)>;
+
+fn find_arrangement(
+    state: &State,
+    move_cost: &HashMap<char, usize>,
+    home_room: &HashMap<char, usize>,
+) -> Solution {
     let mut queue = Vec::new();
-    let mut visited = HashMap::new();
+    let mut visited: Visited = HashMap::new();
     let init_state = Rc::new(state.clone());
     visited.insert(init_state.clone(), (0_usize, None));
     queue.push((init_state, 0_usize));
@@ -151,66 +237,632 @@ fn find_arrange_cost(
         queue.sort_by_key(|(_, cost)| std::cmp::Reverse(*cost));
         let (state, cost) = queue.pop().unwrap();
         if is_state_final(&state, home_room) {
-            // let mut s = Some(state.clone());
-            // while let Some(state) = s {
-            //     println!("{:?}", state);
-            //     s = visited
-            //         .get(&state)
-            //         .map(|(cost, prev_state)| prev_state.clone())
-            //         .flatten();
-            // }
-
-            return cost;
+            let mut moves = Vec::new();
+            let mut s = state;
+            while let Some((_, Some((prev_state, m)))) = visited.get(&s) {
+                moves.push(*m);
+                s = prev_state.clone();
+            }
+            moves.reverse();
+            return Solution { cost, moves };
         }
-        for (next_state, cost_to_next_state) in make_next_states(&state, move_cost, home_room) {
+        for (next_state, m) in make_next_states(&state, move_cost, home_room) {
             let next_state = Rc::new(next_state);
-            let next_cost = cost + cost_to_next_state;
+            let next_cost = cost + m.cost;
             let existing_cost = visited.get(&next_state).map(|(cost, _)| *cost);
             if existing_cost
                 .map(|existing_cost| existing_cost > next_cost)
                 .unwrap_or(true)
             {
-                visited.insert(next_state.clone(), (next_cost, Some(state.clone())));
+                visited.insert(next_state.clone(), (next_cost, Some((state.clone(), m))));
                 queue.push((next_state, next_cost));
             }
         }
     }
 }
 
-fn make_pt2_input(input: &State) -> State {
+// all cells of hall and rooms packed by 3 bits, 0 is empty cell, otherwise home room number + 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct PackedState(u128);
+
+const CELL_BITS: usize = 3;
+
+impl PackedState {
+    fn pack(state: &State, home_room: &HashMap<char, usize>) -> PackedState {
+        let cells = state.hall.iter().chain(state.rooms.iter().flatten());
+        let mut packed = 0_u128;
+        for (i, c) in cells.enumerate() {
+            let code = if is_apod_char(*c) {
+                *home_room.get(c).unwrap() as u128 + 1
+            } else {
+                0
+            };
+            assert!(
+                code < 1 << CELL_BITS && (i + 1) * CELL_BITS <= 128,
+                "burrow does not fit into packed state"
+            );
+            packed |= code << (i * CELL_BITS);
+        }
+        PackedState(packed)
+    }
+
+    // every cell and every amphipod code have to fit into CELL_BITS bits of u128
+    fn fits(state: &State, home_room: &HashMap<char, usize>) -> bool {
+        let cells = state.hall.len() + state.rooms.iter().map(|room| room.len()).sum::<usize>();
+        home_room.len() < 1 << CELL_BITS && cells * CELL_BITS <= 128
+    }
+
+    // template gives geometry of burrow, apods[room_n] is amphipod that lives in room_n
+    fn unpack(&self, template: &State, apods: &[char]) -> State {
+        let mut state = template.clone();
+        let cells = state
+            .hall
+            .iter_mut()
+            .chain(state.rooms.iter_mut().flatten());
+        for (i, c) in cells.enumerate() {
+            let code = (self.0 >> (i * CELL_BITS)) as usize & ((1 << CELL_BITS) - 1);
+            *c = if code == 0 { '.' } else { apods[code - 1] };
+        }
+        state
+    }
+}
+
+// key of visited states in A*, burrows that do not fit into PackedState are kept as they are
+trait SearchKey: Clone + Eq + Hash + Ord {
+    fn make(state: &State, home_room: &HashMap<char, usize>) -> Self;
+    fn restore(&self, template: &State, apods: &[char]) -> State;
+}
+
+impl SearchKey for PackedState {
+    fn make(state: &State, home_room: &HashMap<char, usize>) -> Self {
+        PackedState::pack(state, home_room)
+    }
+
+    fn restore(&self, template: &State, apods: &[char]) -> State {
+        self.unpack(template, apods)
+    }
+}
+
+impl SearchKey for State {
+    fn make(state: &State, _home_room: &HashMap<char, usize>) -> Self {
+        state.clone()
+    }
+
+    fn restore(&self, _template: &State, _apods: &[char]) -> State {
+        self.clone()
+    }
+}
+
+// lower bound of cost to finish: every amphipod not settled in its room has to walk to top of its room,
+// amphipods in home room over strangers have to step out to hall and back
+fn estimate_cost(
+    state: &State,
+    move_cost: &HashMap<char, usize>,
+    home_room: &HashMap<char, usize>,
+) -> usize {
+    let mut cost = 0;
+    for (x, &apod) in state.hall.iter().enumerate() {
+        if is_apod_char(apod) {
+            let home_x = state.room_coords[*home_room.get(&apod).unwrap()];
+            cost += move_cost.get(&apod).unwrap() * (abs_diff(x, home_x) + 1);
+        }
+    }
+    for (room_n, room) in state.rooms.iter().enumerate() {
+        for (y, &apod) in room.iter().enumerate() {
+            if !is_apod_char(apod) {
+                continue;
+            }
+            let home_n = *home_room.get(&apod).unwrap();
+            let settled = home_n == room_n
+                && room[y..]
+                    .iter()
+                    .all(|c| home_room.get(c) == Some(&room_n));
+            if !settled {
+                let walk = if home_n == room_n {
+                    2
+                } else {
+                    abs_diff(state.room_coords[room_n], state.room_coords[home_n])
+                };
+                cost += move_cost.get(&apod).unwrap() * (y + 1 + walk + 1);
+            }
+        }
+    }
+    cost
+}
+
+// A* over packed states, heuristic is consistent so first final state taken from heap is optimal,
+// None if amphipods are stuck
+fn find_arrangement_astar(
+    state: &State,
+    move_cost: &HashMap<char, usize>,
+    home_room: &HashMap<char, usize>,
+) -> Option<Solution> {
+    if PackedState::fits(state, home_room) {
+        find_arrangement_astar_by::<PackedState>(state, move_cost, home_room)
+    } else {
+        find_arrangement_astar_by::<State>(state, move_cost, home_room)
+    }
+}
+
+fn find_arrangement_astar_by<K: SearchKey>(
+    state: &State,
+    move_cost: &HashMap<char, usize>,
+    home_room: &HashMap<char, usize>,
+) -> Option<Solution> {
+    let mut apods = vec!['.'; state.rooms.len()];
+    for (&apod, &room_n) in home_room.iter() {
+        apods[room_n] = apod;
+    }
+    let start = K::make(state, home_room);
+    let mut costs = HashMap::new();
+    let mut prev: HashMap<K, (K, Move)> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    costs.insert(start.clone(), 0);
+    queue.push(Reverse((estimate_cost(state, move_cost, home_room), 0, start)));
+    while let Some(Reverse((_, cost, key))) = queue.pop() {
+        if cost > *costs.get(&key).unwrap() {
+            continue;
+        }
+        let current = key.restore(state, &apods);
+        if is_state_final(&current, home_room) {
+            let mut moves = Vec::new();
+            let mut k = key;
+            while let Some((prev_key, m)) = prev.get(&k) {
+                moves.push(*m);
+                k = prev_key.clone();
+            }
+            moves.reverse();
+            return Some(Solution { cost, moves });
+        }
+        for (next_state, m) in make_next_states(&current, move_cost, home_room) {
+            let next_key = K::make(&next_state, home_room);
+            let next_cost = cost + m.cost;
+            if costs.get(&next_key).is_none_or(|&c| c > next_cost) {
+                costs.insert(next_key.clone(), next_cost);
+                prev.insert(next_key.clone(), (key.clone(), m));
+                let priority = next_cost + estimate_cost(&next_state, move_cost, home_room);
+                queue.push(Reverse((priority, next_cost, next_key)));
+            }
+        }
+    }
+    None
+}
+
+fn benchmark(
+    name: &str,
+    state: &State,
+    move_cost: &HashMap<char, usize>,
+    home_room: &HashMap<char, usize>,
+) -> usize {
+    let start = Instant::now();
+    let sorted_vec_cost = find_arrange_cost(state, move_cost, home_room);
+    let sorted_vec_time = start.elapsed();
+    let start = Instant::now();
+    let astar_cost = find_arrangement_astar(state, move_cost, home_room)
+        .unwrap()
+        .cost;
+    let astar_time = start.elapsed();
+    assert_eq!(sorted_vec_cost, astar_cost);
+    println!(
+        "day23 benchmark {}: sorted vec {:?}, A* {:?}",
+        name, sorted_vec_time, astar_time
+    );
+    astar_cost
+}
+
+// prints every intermediate state of solution
+fn print_solution(state: &State, solution: &Solution) {
+    let mut state = state.clone();
+    print!("{:?}", state);
+    let mut total = 0;
+    for m in solution.moves.iter() {
+        state = state.apply_move(m);
+        total += m.cost;
+        println!("{}, total {}", m, total);
+        print!("{:?}", state);
+    }
+}
+
+fn test_solution(
+    state: &State,
+    move_cost: &HashMap<char, usize>,
+    home_room: &HashMap<char, usize>,
+) {
+    let solution = find_arrangement(state, move_cost, home_room);
+    assert_eq!(solution.cost, 12521);
+    assert_eq!(
+        solution.moves.iter().map(|m| m.cost).sum::<usize>(),
+        solution.cost
+    );
+    // every move is one of legal moves from previous state
+    let mut s = state.clone();
+    for m in solution.moves.iter() {
+        let next_states = make_next_states(&s, move_cost, home_room);
+        assert!(next_states.iter().any(|(_, next_move)| next_move == m));
+        s = s.apply_move(m);
+    }
+    assert!(is_state_final(&s, home_room));
+}
+
+// plays puzzle with commands read from input: number of move to make, "u" to undo last move,
+// "h" for optimal remaining energy and next move, "q" to quit; returns spent energy
+fn play(
+    state: &State,
+    rules: &Rules,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> io::Result<usize> {
+    let (move_cost, home_room) = (&rules.move_cost, &rules.home_room);
+    let mut history: Vec<(State, Move)> = Vec::new();
+    let mut state = state.clone();
+    loop {
+        let energy = history.iter().map(|(_, m)| m.cost).sum::<usize>();
+        write!(output, "{:?}", state)?;
+        writeln!(output, "energy {}", energy)?;
+        if is_state_final(&state, home_room) {
+            writeln!(output, "done, energy {}", energy)?;
+            return Ok(energy);
+        }
+        let next_states = make_next_states(&state, move_cost, home_room);
+        if next_states.is_empty() {
+            writeln!(output, "no moves left, undo with u")?;
+        }
+        for (i, (_, m)) in next_states.iter().enumerate() {
+            writeln!(output, "{}: {}", i, m)?;
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(energy);
+        }
+        match line.trim() {
+            "q" => return Ok(energy),
+            "u" => match history.pop() {
+                Some((prev_state, m)) => {
+                    writeln!(output, "undo {}", m)?;
+                    state = prev_state;
+                }
+                None => writeln!(output, "nothing to undo")?,
+            },
+            "h" => match find_arrangement_astar(&state, move_cost, home_room) {
+                Some(solution) => {
+                    writeln!(output, "optimal remaining energy {}", solution.cost)?;
+                    if let Some(m) = solution.moves.first() {
+                        writeln!(output, "next move {}", m)?;
+                    }
+                }
+                None => writeln!(output, "amphipods are stuck, undo some moves")?,
+            },
+            cmd => match cmd.parse::<usize>().ok().and_then(|i| next_states.get(i)) {
+                Some((next_state, m)) => {
+                    history.push((state, *m));
+                    state = next_state.clone();
+                }
+                None => writeln!(output, "unknown command {}", cmd)?,
+            },
+        }
+    }
+}
+
+// map and its rules selected by args: map name from input and "unfold" to play part 2 map,
+// map uses "<map> rules" section if there is one and "rules" otherwise
+fn select_game<'a>(
+    input: &HashMap<String, State>,
+    rules_by_name: &'a HashMap<String, Rules>,
+    args: &[String],
+) -> Result<(State, &'a Rules), String> {
+    let name = args.first().map(|s| s.as_str()).unwrap_or("day23");
+    let mut state = input.get(name).ok_or(format!("no map {}", name))?.clone();
+    let rules = rules_by_name
+        .get(&format!("{} rules", name))
+        .or(rules_by_name.get("rules"))
+        .ok_or(format!("no rules for map {}", name))?;
+    if args.iter().any(|arg| arg == "unfold") {
+        state = unfold_input(&state, rules)?;
+    }
+    rules.check(&state)?;
+    Ok((state, rules))
+}
+
+// runs player on stdin with map selected by args
+pub fn play_interactive(args: &[String]) {
+    let (input, rules_by_name) = parse_input(&std::fs::read_to_string("input/day23.txt").unwrap());
+    match select_game(&input, &rules_by_name, args) {
+        Ok((state, rules)) => {
+            play(&state, rules, &mut io::stdin().lock(), &mut io::stdout()).unwrap();
+        }
+        Err(e) => eprintln!("day23 play: {}", e),
+    }
+}
+
+fn test_select_game(input: &HashMap<String, State>, rules_by_name: &HashMap<String, Rules>) {
+    let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+    let (state, rules) = select_game(input, rules_by_name, &args("")).unwrap();
+    assert_eq!(state, *input.get("day23").unwrap());
+    assert_eq!(rules.home_room.len(), 4);
+    let (state, rules) = select_game(input, rules_by_name, &args("variant")).unwrap();
+    assert_eq!((state.rooms.len(), rules.home_room.len()), (5, 5));
+    let (state, _) = select_game(input, rules_by_name, &args("variant unfold")).unwrap();
+    assert_eq!(state.rooms[0].len(), 2);
+    assert_eq!(
+        select_game(input, rules_by_name, &args("nosuch")).unwrap_err(),
+        "no map nosuch"
+    );
+    assert_eq!(
+        select_game(input, &HashMap::new(), &args("test")).unwrap_err(),
+        "no rules for map test"
+    );
+    // rules that do not fit the map are reported instead of panicking
+    let mut wrong_rules = HashMap::new();
+    wrong_rules.insert(
+        "rules".to_string(),
+        parse_rules(&["A 1", "B 10", "C 100", "D 1000"]),
+    );
+    assert_eq!(
+        select_game(input, &wrong_rules, &args("variant")).unwrap_err(),
+        "4 species for 5 rooms"
+    );
+    // unfold rows of the other map
+    let mut wrong_rules = HashMap::new();
+    wrong_rules.insert(
+        "rules".to_string(),
+        parse_rules(&[
+            "A 1",
+            "B 10",
+            "C 100",
+            "D 1000",
+            "E 10000",
+            "unfold",
+            "  #D#C#B#A#",
+        ]),
+    );
+    assert_eq!(
+        select_game(input, &wrong_rules, &args("variant unfold")).unwrap_err(),
+        "unfold row #D#C#B#A# does not fit rooms of map"
+    );
+    wrong_rules.insert(
+        "rules".to_string(),
+        parse_rules(&["A 1", "B 10", "C 100", "D 1000", "unfold", "  #D#C#B#a#"]),
+    );
+    assert_eq!(
+        select_game(input, &wrong_rules, &args("test unfold")).unwrap_err(),
+        "unexpected char a in unfold row #D#C#B#a#"
+    );
+    // unknown amphipod in unfold rows
+    wrong_rules.insert(
+        "rules".to_string(),
+        parse_rules(&["A 1", "B 10", "C 100", "D 1000", "unfold", "  #D#C#B#E#"]),
+    );
+    assert_eq!(
+        select_game(input, &wrong_rules, &args("test unfold")).unwrap_err(),
+        "unknown amphipod E"
+    );
+}
+
+fn test_play(state: &State, rules: &Rules) {
+    // script plays optimal solution found by solver with a wrong command and an undo on the way
+    let solution = find_arrangement_astar(state, &rules.move_cost, &rules.home_room).unwrap();
+    let mut script = String::from("h\nx\n0\nu\nu\n");
+    let mut s = state.clone();
+    for m in solution.moves.iter() {
+        let next_states = make_next_states(&s, &rules.move_cost, &rules.home_room);
+        let i = next_states.iter().position(|(_, next)| next == m).unwrap();
+        script += &format!("{}\n", i);
+        s = s.apply_move(m);
+    }
+    let mut output = Vec::new();
+    let energy = play(state, rules, &mut script.as_bytes(), &mut output).unwrap();
+    assert_eq!(energy, 12521);
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("optimal remaining energy 12521\nnext move "));
+    assert!(output.contains("unknown command x\n"));
+    assert!(output.contains("> undo "));
+    assert!(output.contains("nothing to undo\n"));
+    assert!(output.ends_with("energy 12521\ndone, energy 12521\n"));
+
+    // quitting in the middle returns energy spent so far
+    let mut output = Vec::new();
+    let energy = play(state, rules, &mut "0\nq\n".as_bytes(), &mut output).unwrap();
+    let first = &make_next_states(state, &rules.move_cost, &rules.home_room)[0].1;
+    assert_eq!(energy, first.cost);
+
+    // amphipods that block each other in hall
+    let stuck = State {
+        hall: "...D.A.....".chars().collect(),
+        rooms: vec![
+            vec!['.', 'A'],
+            vec!['B', 'B'],
+            vec!['C', 'C'],
+            vec!['.', 'D'],
+        ],
+        room_coords: vec![2, 4, 6, 8],
+    };
+    let mut output = Vec::new();
+    play(&stuck, rules, &mut "h\n".as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("no moves left, undo with u\n"));
+    assert!(output.contains("amphipods are stuck, undo some moves\n"));
+}
+
+// burrow rules given by input: species in order of their rooms with energy per step,
+// and rows inserted after the first room row when the map is unfolded
+#[derive(Debug)]
+struct Rules {
+    move_cost: HashMap<char, usize>,
+    home_room: HashMap<char, usize>,
+    unfold: Vec<String>,
+}
+
+impl Rules {
+    // one species per room, and every amphipod on the map is known
+    fn check(&self, state: &State) -> Result<(), String> {
+        if self.home_room.len() != state.rooms.len() {
+            return Err(format!(
+                "{} species for {} rooms",
+                self.home_room.len(),
+                state.rooms.len()
+            ));
+        }
+        for c in state.hall.iter().chain(state.rooms.iter().flatten()) {
+            if is_apod_char(*c) && !self.home_room.contains_key(c) {
+                return Err(format!("unknown amphipod {}", c));
+            }
+        }
+        Ok(())
+    }
+}
+
+// rows are checked against rooms of the map, so rules that do not fit it are reported
+// instead of failing in parse_state
+fn unfold_input(input: &State, rules: &Rules) -> Result<State, String> {
+    for row in rules.unfold.iter() {
+        if let Some(c) = row
+            .chars()
+            .find(|&c| !matches!(c, ' ' | '#') && !is_apod_char(c))
+        {
+            return Err(format!(
+                "unexpected char {} in unfold row {}",
+                c,
+                row.trim()
+            ));
+        }
+        let coords = row
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| is_apod_char(*c))
+            .map(|(i, _)| i);
+        if !coords.eq(input.room_coords.iter().map(|pos| pos + 1)) {
+            return Err(format!(
+                "unfold row {} does not fit rooms of map",
+                row.trim()
+            ));
+        }
+    }
     let s = format!("{:?}", input);
     let mut lines: Vec<_> = s.lines().collect();
-    lines.insert(3, "  #D#C#B#A#");
-    lines.insert(4, "  #D#B#A#C#");
-    parse_state(&lines)
+    for (i, row) in rules.unfold.iter().enumerate() {
+        lines.insert(3 + i, row);
+    }
+    Ok(parse_state(&lines))
 }
 
 pub fn main() {
-    let move_cost: HashMap<char, usize> = [('A', 1), ('B', 10), ('C', 100), ('D', 1000)]
-        .into_iter()
-        .collect();
-    let home_room: HashMap<char, usize> = [('A', 0), ('B', 1), ('C', 2), ('D', 3)]
-        .into_iter()
-        .collect();
-
-    let input = parse_input(&std::fs::read_to_string("input/day23.txt").unwrap());
+    let (input, rules_by_name) = parse_input(&std::fs::read_to_string("input/day23.txt").unwrap());
+    let rules = rules_by_name.get("rules").unwrap();
+    let (move_cost, home_room) = (&rules.move_cost, &rules.home_room);
     let test_input = input.get("test").unwrap();
-    let test_input_pt2 = make_pt2_input(&test_input);
+    let test_input_pt2 = unfold_input(test_input, rules).unwrap();
     let day23_input = input.get("day23").unwrap();
-    let day23_input_pt2 = make_pt2_input(&day23_input);
-    assert_eq!(12521, find_arrange_cost(test_input, &move_cost, &home_room));
-    assert_eq!(44169, find_arrange_cost(&test_input_pt2, &move_cost, &home_room));
+    let day23_input_pt2 = unfold_input(day23_input, rules).unwrap();
+    rules.check(&test_input_pt2).unwrap();
+    rules.check(&day23_input_pt2).unwrap();
+    assert_eq!(12521, find_arrange_cost(test_input, move_cost, home_room));
+    test_solution(test_input, move_cost, home_room);
+    assert_eq!(
+        44169,
+        find_arrange_cost(&test_input_pt2, move_cost, home_room)
+    );
+    test_astar(test_input, move_cost, home_room);
+    assert_eq!(
+        44169,
+        find_arrangement_astar(&test_input_pt2, move_cost, home_room)
+            .unwrap()
+            .cost
+    );
+    test_variant(&input, &rules_by_name);
+    test_play(test_input, rules);
+    test_select_game(&input, &rules_by_name);
     println!("tests ok");
+    print_solution(
+        test_input,
+        &find_arrangement(test_input, move_cost, home_room),
+    );
     println!(
         "day23 pt1 {}",
-        find_arrange_cost(day23_input, &move_cost, &home_room)
+        benchmark("pt1", day23_input, move_cost, home_room)
     );
     println!(
         "day23 pt2 {}",
-        find_arrange_cost(&day23_input_pt2, &move_cost, &home_room)
+        benchmark("pt2", &day23_input_pt2, move_cost, home_room)
     );
 }
 
+// five rooms with species E, neighbours in rooms 0, 1 and 3, 4 are swapped
+fn test_variant(input: &HashMap<String, State>, rules: &HashMap<String, Rules>) {
+    let state = input.get("variant").unwrap();
+    let rules = rules.get("variant rules").unwrap();
+    rules.check(state).unwrap();
+    assert_eq!(rules.move_cost.get(&'E'), Some(&10000));
+    assert_eq!(state.rooms.len(), 5);
+    let (move_cost, home_room) = (&rules.move_cost, &rules.home_room);
+    assert_eq!(find_arrange_cost(state, move_cost, home_room), 46046);
+    assert_eq!(
+        find_arrangement_astar(state, move_cost, home_room)
+            .unwrap()
+            .cost,
+        46046
+    );
+    // unfolded variant has the same amphipods twice as deep
+    let unfolded = unfold_input(state, rules).unwrap();
+    assert_eq!(unfolded.rooms[0], vec!['B', 'B']);
+    assert!(
+        find_arrangement_astar(&unfolded, move_cost, home_room)
+            .unwrap()
+            .cost
+            > 46046
+    );
+}
+
+fn test_astar(
+    state: &State,
+    move_cost: &HashMap<char, usize>,
+    home_room: &HashMap<char, usize>,
+) {
+    let apods = ['A', 'B', 'C', 'D'];
+    let packed = PackedState::pack(state, home_room);
+    assert_eq!(packed.unpack(state, &apods), *state);
+    let (next_state, _) = &make_next_states(state, move_cost, home_room)[0];
+    assert_ne!(PackedState::pack(next_state, home_room), packed);
+
+    let solution = find_arrangement_astar(state, move_cost, home_room).unwrap();
+    assert_eq!(solution.cost, 12521);
+    // heuristic never overestimates remaining cost along optimal path and is 0 at the end
+    let mut s = state.clone();
+    let mut remaining = solution.cost;
+    for m in solution.moves.iter() {
+        assert!(estimate_cost(&s, move_cost, home_room) <= remaining);
+        s = s.apply_move(m);
+        remaining -= m.cost;
+    }
+    assert_eq!(remaining, 0);
+    assert!(is_state_final(&s, home_room));
+    assert_eq!(estimate_cost(&s, move_cost, home_room), 0);
+    // D in its room over A still has to step out and back: 1000 * 4 of 11489
+    assert_eq!(estimate_cost(state, move_cost, home_room), 11489);
+
+    // 7 rooms 4 deep are 45 cells, too many for packed state, search works on plain states
+    let apods = "ABCDEFG".chars().collect::<Vec<_>>();
+    let mut rooms = apods.iter().map(|&apod| vec![apod; 4]).collect::<Vec<_>>();
+    rooms[0][0] = 'B';
+    rooms[1][0] = 'A';
+    let wide = State {
+        hall: vec!['.'; 17],
+        rooms,
+        room_coords: vec![2, 4, 6, 8, 10, 12, 14],
+    };
+    let wide_move_cost = apods.iter().map(|&apod| (apod, 10)).collect();
+    let wide_home_room = apods
+        .iter()
+        .enumerate()
+        .map(|(n, &apod)| (apod, n))
+        .collect();
+    assert!(!PackedState::fits(&wide, &wide_home_room));
+    // A steps aside, B goes straight to its room, A comes back: 2 + 4 + 4 steps
+    let solution = find_arrangement_astar(&wide, &wide_move_cost, &wide_home_room).unwrap();
+    assert_eq!(solution.cost, 100);
+}
+
 fn parse_state(lines: &[&str]) -> State {
     let line0 = lines[0];
     let hall_size = line0.len() - 2;
@@ -255,14 +907,46 @@ fn parse_state(lines: &[&str]) -> State {
     }
 }
 
-fn parse_input(s: &str) -> HashMap<String, State> {
-    s.split("\n\n")
-        .map(|s| {
-            let lines: Vec<_> = s.split("\n").collect();
-            let name = lines[0].to_string();
-            (name, parse_state(&lines[1..]))
-        })
-        .collect()
+// species lines "A 1" in order of rooms, optionally followed by "unfold" and rows to insert
+fn parse_rules(lines: &[&str]) -> Rules {
+    let mut move_cost = HashMap::new();
+    let mut home_room = HashMap::new();
+    let mut unfold = Vec::new();
+    let mut is_unfold = false;
+    for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+        if is_unfold {
+            unfold.push(line.to_string());
+        } else if *line == "unfold" {
+            is_unfold = true;
+        } else {
+            let (apod, cost) = line.split_once(' ').unwrap();
+            let apod = apod.chars().next().unwrap();
+            assert!(is_apod_char(apod), "bad amphipod {}", apod);
+            home_room.insert(apod, home_room.len());
+            move_cost.insert(apod, cost.parse().unwrap());
+        }
+    }
+    Rules {
+        move_cost,
+        home_room,
+        unfold,
+    }
+}
+
+// sections with name ending with "rules" are rules, other sections are burrow maps
+fn parse_input(s: &str) -> (HashMap<String, State>, HashMap<String, Rules>) {
+    let mut states = HashMap::new();
+    let mut rules = HashMap::new();
+    for s in s.split("\n\n") {
+        let lines: Vec<_> = s.split("\n").collect();
+        let name = lines[0].to_string();
+        if name.ends_with("rules") {
+            rules.insert(name, parse_rules(&lines[1..]));
+        } else {
+            states.insert(name, parse_state(&lines[1..]));
+        }
+    }
+    (states, rules)
 }
 
 impl std::fmt::Debug for State {
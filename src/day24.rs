@@ -3,58 +3,107 @@ We have an interpreter of ALU instructions that operate Ranges
 To find maximum number we start with number consisting of digits, where each digit is a range [1..9]
 Then we try ranges [1..1], [2..2] .. [9..9] for every digit in number, but continue to next digit only if resulting range for z includes 0.
 */
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
 
-#[derive(Debug, Clone, Copy)]
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Register(usize);
 
 impl Register {
     fn from_name(name: &str) -> Self {
+        Self::parse(name).unwrap_or_else(|_| panic!("bad register name {}", name))
+    }
+
+    fn parse(name: &str) -> Result<Self, AsmError> {
         match name {
-            "w" => Self(0),
-            "x" => Self(1),
-            "y" => Self(2),
-            "z" => Self(3),
-            _ => panic!("bad register name {}", name),
+            "w" => Ok(Self(0)),
+            "x" => Ok(Self(1)),
+            "y" => Ok(Self(2)),
+            "z" => Ok(Self(3)),
+            _ => Err(AsmError::BadRegister(name.to_string())),
         }
     }
+
+    fn name(&self) -> char {
+        ['w', 'x', 'y', 'z'][self.0]
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Arg {
     Register(Register),
     Value(isize),
 }
 
 impl Arg {
-    fn parse(s: &str) -> Self {
-        if let Ok(val) = s.parse::<isize>() {
-            Self::Value(val)
+    fn parse(s: &str) -> Result<Self, AsmError> {
+        let digits = s.strip_prefix('-').unwrap_or(s);
+        if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+            s.parse::<isize>()
+                .map(Self::Value)
+                .map_err(|_| AsmError::LiteralOutOfRange(s.to_string()))
         } else {
-            Self::Register(Register::from_name(s))
+            Register::parse(s).map(Self::Register)
+        }
+    }
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arg::Register(r) => write!(f, "{}", r.name()),
+            Arg::Value(v) => write!(f, "{}", v),
         }
     }
 }
 
-#[derive(PartialEq, Debug)]
-struct State([Range; 4]);
+// abstract value of register: set of all values register can have
+// every operation must be sound: result contains every concrete result for operands from the sets,
+// operations that would fail on concrete interpreter can be ignored
+trait AbstractValue: Clone + PartialEq + fmt::Debug + fmt::Display {
+    fn from_values(values: &[isize]) -> Self;
+    fn as_constant(&self) -> Option<isize>;
+    fn contains(&self, value: isize) -> bool;
+    fn add(&self, rhs: &Self) -> Self;
+    fn mul(&self, rhs: &Self) -> Self;
+    fn div(&self, rhs: &Self) -> Self;
+    fn modulo(&self, rhs: &Self) -> Self;
+    fn eql(&self, rhs: &Self) -> Self;
+}
+
+#[derive(PartialEq, Debug, Clone)]
+struct State<T = Range>([T; 4]);
 
-impl State {
-    fn new() -> State {
-        State([Range::new(0, 0); 4])
+impl<T: AbstractValue> State<T> {
+    fn new() -> State<T> {
+        State(std::array::from_fn(|_| T::from_values(&[0])))
     }
 
-    fn get_register(&self, register: Register) -> &Range {
+    fn get_register(&self, register: Register) -> &T {
         &self.0[register.0]
     }
 
-    fn get_register_by_name(&self, name: &str) -> &Range {
+    fn get_register_by_name(&self, name: &str) -> &T {
         self.get_register(Register::from_name(name))
     }
 
-    fn set_register(&mut self, register: Register, value: Range) {
+    fn set_register(&mut self, register: Register, value: T) {
         self.0[register.0] = value
     }
+
+    fn get_arg(&self, arg: &Arg) -> T {
+        match arg {
+            Arg::Register(r) => self.get_register(*r).clone(),
+            Arg::Value(v) => T::from_values(&[*v]),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -80,17 +129,6 @@ impl Range {
         Range::new(self.from + rhs.from, self.to + rhs.to)
     }
 
-    fn add_value(&self, rhs: isize) -> Self {
-        Range::new(self.from + rhs, self.to + rhs)
-    }
-
-    fn add(&self, state: &State, rhs: &Arg) -> Self {
-        match rhs {
-            Arg::Register(r) => self.add_range(state.get_register(*r)),
-            Arg::Value(v) => self.add_value(*v),
-        }
-    }
-
     fn mul_range(&self, rhs: &Self) -> Self {
         Range::from_multi_values(&[
             self.from * rhs.from,
@@ -104,22 +142,28 @@ impl Range {
         Range::new(self.from * rhs, self.to * rhs)
     }
 
-    fn mul(&self, state: &State, rhs: &Arg) -> Self {
-        match rhs {
-            Arg::Register(r) => self.mul_range(state.get_register(*r)),
-            Arg::Value(v) => self.mul_value(*v),
-        }
-    }
-
     fn div_value(&self, rhs: isize) -> Self {
         Range::new(self.from / rhs, self.to / rhs)
     }
 
-    fn div(&self, _state: &State, rhs: &Arg) -> Self {
-        match rhs {
-            Arg::Register(_) => panic!("div by register not implemented"),
-            Arg::Value(v) => self.div_value(*v),
+    fn div_range(&self, rhs: &Self) -> Self {
+        if rhs.from == rhs.to && rhs.from != 0 {
+            return self.div_value(rhs.from);
         }
+        // extreme values are at the ends of ranges, divisor can't be 0 but can be next to it
+        let divisors = [rhs.from, rhs.to, -1, 1]
+            .into_iter()
+            .filter(|&d| d != 0 && rhs.from <= d && d <= rhs.to)
+            .collect::<Vec<_>>();
+        if divisors.is_empty() {
+            return *self;
+        }
+        Range::from_multi_values(
+            &divisors
+                .iter()
+                .flat_map(|&d| [self.from / d, self.to / d])
+                .collect::<Vec<_>>(),
+        )
     }
 
     fn mod_value(&self, rhs: isize) -> Self {
@@ -148,11 +192,12 @@ impl Range {
         }
     }
 
-    fn modulo(&self, _state: &State, rhs: &Arg) -> Self {
-        match rhs {
-            Arg::Register(_) => panic!("mod by register not implemented"),
-            Arg::Value(v) => self.mod_value(*v),
+    fn mod_range(&self, rhs: &Self) -> Self {
+        if rhs.from == rhs.to && rhs.from > 0 {
+            return self.mod_value(rhs.from);
         }
+        // concrete mod works only with non negative value and positive divisor
+        Range::new(0, std::cmp::max(0, std::cmp::min(self.to, rhs.to - 1)))
     }
 
     fn eql_range(&self, rhs: &Self) -> Self {
@@ -179,16 +224,9 @@ impl Range {
             Range::new(0, 1)
         }
     }
-
-    fn eql(&self, state: &State, rhs: &Arg) -> Self {
-        match rhs {
-            Arg::Register(r) => self.eql_range(state.get_register(*r)),
-            Arg::Value(v) => self.eql_value(*v),
-        }
-    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Command {
     Inp(Register),
     Add(Register, Arg),
@@ -198,101 +236,1330 @@ enum Command {
     Eql(Register, Arg),
 }
 
-fn parse_command(s: &str) -> Command {
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Inp(r) => write!(f, "inp {}", r.name()),
+            Command::Add(r, a) => write!(f, "add {} {}", r.name(), a),
+            Command::Mul(r, a) => write!(f, "mul {} {}", r.name(), a),
+            Command::Div(r, a) => write!(f, "div {} {}", r.name(), a),
+            Command::Mod(r, a) => write!(f, "mod {} {}", r.name(), a),
+            Command::Eql(r, a) => write!(f, "eql {} {}", r.name(), a),
+        }
+    }
+}
+
+impl AbstractValue for Range {
+    fn from_values(values: &[isize]) -> Self {
+        Range::from_multi_values(values)
+    }
+
+    fn as_constant(&self) -> Option<isize> {
+        if self.from == self.to {
+            Some(self.from)
+        } else {
+            None
+        }
+    }
+
+    fn contains(&self, value: isize) -> bool {
+        self.from <= value && value <= self.to
+    }
+
+    fn add(&self, rhs: &Self) -> Self {
+        self.add_range(rhs)
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        self.mul_range(rhs)
+    }
+
+    fn div(&self, rhs: &Self) -> Self {
+        self.div_range(rhs)
+    }
+
+    fn modulo(&self, rhs: &Self) -> Self {
+        self.mod_range(rhs)
+    }
+
+    fn eql(&self, rhs: &Self) -> Self {
+        self.eql_range(rhs)
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.from == self.to {
+            write!(f, "{}", self.from)
+        } else {
+            write!(f, "[{}..{}]", self.from, self.to)
+        }
+    }
+}
+
+const MAX_SET_LEN: usize = 32;
+
+fn gcd(a: isize, b: isize) -> isize {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// values from..=to that differ from `from` by multiple of step, step is 0 only when from == to
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Strided {
+    from: isize,
+    to: isize,
+    step: isize,
+}
+
+impl Strided {
+    fn new(from: isize, to: isize, step: isize) -> Strided {
+        assert!(from <= to);
+        let step = if from == to { 0 } else { step.abs().max(1) };
+        let to = if step == 0 {
+            to
+        } else {
+            from + (to - from) / step * step
+        };
+        let step = if from == to { 0 } else { step };
+        Strided { from, to, step }
+    }
+
+    fn from_set(values: &BTreeSet<isize>) -> Strided {
+        let from = *values.iter().next().unwrap();
+        let to = *values.iter().next_back().unwrap();
+        let step = values.iter().fold(0, |acc, v| gcd(acc, v - from));
+        Strided::new(from, to, step)
+    }
+
+    fn len(&self) -> usize {
+        if self.step == 0 {
+            1
+        } else {
+            ((self.to - self.from) / self.step + 1) as usize
+        }
+    }
+
+    fn contains(&self, value: isize) -> bool {
+        self.from <= value
+            && value <= self.to
+            && (self.step == 0 || (value - self.from) % self.step == 0)
+    }
+
+    fn add(&self, rhs: &Self) -> Self {
+        Strided::new(
+            self.from + rhs.from,
+            self.to + rhs.to,
+            gcd(self.step, rhs.step),
+        )
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        // (a + i * s) * (b + j * t) = a * b + a * j * t + b * i * s + i * j * s * t
+        let corners = [
+            self.from * rhs.from,
+            self.from * rhs.to,
+            self.to * rhs.from,
+            self.to * rhs.to,
+        ];
+        let step = gcd(
+            gcd(self.from * rhs.step, rhs.from * self.step),
+            self.step * rhs.step,
+        );
+        Strided::new(
+            *corners.iter().min().unwrap(),
+            *corners.iter().max().unwrap(),
+            step,
+        )
+    }
+
+    fn div(&self, rhs: &Self) -> Self {
+        let range = Range::new(self.from, self.to).div_range(&Range::new(rhs.from, rhs.to));
+        // (a + i * s) / d = a / d + i * (s / d) if d divides s and there is no rounding towards zero
+        let step = if rhs.step == 0 && rhs.from > 0 && self.from >= 0 && self.step % rhs.from == 0 {
+            self.step / rhs.from
+        } else {
+            1
+        };
+        Strided::new(range.from, range.to, step)
+    }
+
+    fn modulo(&self, rhs: &Self) -> Self {
+        if rhs.step != 0 || rhs.from <= 0 || self.from < 0 {
+            let range = Range::new(self.from, self.to).mod_range(&Range::new(rhs.from, rhs.to));
+            return Strided::new(range.from, range.to, 1);
+        }
+        let m = rhs.from;
+        if self.from / m == self.to / m {
+            // no wrap around
+            return Strided::new(self.from % m, self.to % m, self.step);
+        }
+        // all values keep remainder by gcd(step, m)
+        let g = gcd(self.step, m);
+        let from = self.from % g;
+        Strided::new(from, m - 1, g)
+    }
+
+    fn eql(&self, rhs: &Self) -> Self {
+        if self.step == 0 && rhs.step == 0 && self.from == rhs.from {
+            Strided::new(1, 1, 0)
+        } else if self.to < rhs.from
+            || rhs.to < self.from
+            || (self.from - rhs.from) % gcd(self.step, rhs.step).max(1) != 0
+        {
+            Strided::new(0, 0, 0)
+        } else {
+            Strided::new(0, 1, 1)
+        }
+    }
+}
+
+// exact set of values while it is small, strided interval when it grows
+// for MONAD search it prunes the same as Range and is much slower: z * {1, 26} loses the step
+// as soon as z becomes strided, it helps only when few digits are unknown
+#[derive(Debug, Clone, PartialEq)]
+enum ValueSet {
+    Set(BTreeSet<isize>),
+    Strided(Strided),
+}
+
+impl ValueSet {
+    fn from_set(values: BTreeSet<isize>) -> ValueSet {
+        if values.len() > MAX_SET_LEN {
+            ValueSet::Strided(Strided::from_set(&values))
+        } else {
+            ValueSet::Set(values)
+        }
+    }
+
+    fn from_strided(strided: Strided) -> ValueSet {
+        if strided.len() <= MAX_SET_LEN {
+            ValueSet::Set(
+                (0..strided.len() as isize)
+                    .map(|i| strided.from + i * strided.step)
+                    .collect(),
+            )
+        } else {
+            ValueSet::Strided(strided)
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        matches!(self, ValueSet::Set(values) if values.is_empty())
+    }
+
+    fn to_strided(&self) -> Strided {
+        match self {
+            ValueSet::Set(values) => Strided::from_set(values),
+            ValueSet::Strided(strided) => *strided,
+        }
+    }
+
+    // applies concrete operation to every pair of values when both sets are small,
+    // operation returns None when it fails on concrete interpreter
+    fn combine<F, G>(&self, rhs: &Self, concrete: F, strided: G) -> Self
+    where
+        F: Fn(isize, isize) -> Option<isize>,
+        G: Fn(&Strided, &Strided) -> Strided,
+    {
+        match (self, rhs) {
+            _ if self.is_empty() || rhs.is_empty() => ValueSet::Set(BTreeSet::new()),
+            (ValueSet::Set(a), ValueSet::Set(b)) => ValueSet::from_set(
+                a.iter()
+                    .flat_map(|&a| b.iter().filter_map(|&b| concrete(a, b)).collect::<Vec<_>>())
+                    .collect(),
+            ),
+            _ => ValueSet::from_strided(strided(&self.to_strided(), &rhs.to_strided())),
+        }
+    }
+}
+
+impl AbstractValue for ValueSet {
+    fn from_values(values: &[isize]) -> Self {
+        ValueSet::from_set(values.iter().cloned().collect())
+    }
+
+    fn as_constant(&self) -> Option<isize> {
+        match self {
+            ValueSet::Set(values) if values.len() == 1 => values.iter().next().cloned(),
+            _ => None,
+        }
+    }
+
+    fn contains(&self, value: isize) -> bool {
+        match self {
+            ValueSet::Set(values) => values.contains(&value),
+            ValueSet::Strided(strided) => strided.contains(value),
+        }
+    }
+
+    fn add(&self, rhs: &Self) -> Self {
+        self.combine(rhs, |a, b| Some(a + b), Strided::add)
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        self.combine(rhs, |a, b| Some(a * b), Strided::mul)
+    }
+
+    fn div(&self, rhs: &Self) -> Self {
+        self.combine(
+            rhs,
+            |a, b| if b == 0 { None } else { Some(a / b) },
+            Strided::div,
+        )
+    }
+
+    fn modulo(&self, rhs: &Self) -> Self {
+        self.combine(
+            rhs,
+            |a, b| if a < 0 || b <= 0 { None } else { Some(a % b) },
+            Strided::modulo,
+        )
+    }
+
+    fn eql(&self, rhs: &Self) -> Self {
+        self.combine(rhs, |a, b| Some((a == b) as isize), Strided::eql)
+    }
+}
+
+impl fmt::Display for ValueSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValueSet::Set(values) => write!(
+                f,
+                "{{{}}}",
+                values
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            ValueSet::Strided(s) => write!(f, "[{}..{} step {}]", s.from, s.to, s.step),
+        }
+    }
+}
+
+impl Command {
+    // register that receives the result
+    fn register(&self) -> Register {
+        match self {
+            Command::Inp(r)
+            | Command::Add(r, _)
+            | Command::Mul(r, _)
+            | Command::Div(r, _)
+            | Command::Mod(r, _)
+            | Command::Eql(r, _) => *r,
+        }
+    }
+
+    fn arg(&self) -> Option<&Arg> {
+        match self {
+            Command::Inp(_) => None,
+            Command::Add(_, a)
+            | Command::Mul(_, a)
+            | Command::Div(_, a)
+            | Command::Mod(_, a)
+            | Command::Eql(_, a) => Some(a),
+        }
+    }
+
+    // instructions that can fail on ALU are never removed,
+    // overflow is a limit of the i64 interpreter and is not kept
+    fn can_fail(&self) -> bool {
+        match self {
+            Command::Div(_, Arg::Value(v)) => *v == 0,
+            Command::Div(..) | Command::Mod(..) => true,
+            _ => false,
+        }
+    }
+
+    // same command with other argument, inp is returned unchanged
+    fn with_arg(&self, arg: Arg) -> Command {
+        match self {
+            Command::Inp(r) => Command::Inp(*r),
+            Command::Add(r, _) => Command::Add(*r, arg),
+            Command::Mul(r, _) => Command::Mul(*r, arg),
+            Command::Div(r, _) => Command::Div(*r, arg),
+            Command::Mod(r, _) => Command::Mod(*r, arg),
+            Command::Eql(r, _) => Command::Eql(*r, arg),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum AsmError {
+    UnknownMnemonic(String),
+    WrongArity(String, usize), // mnemonic and number of operands
+    BadRegister(String),
+    LiteralOutOfRange(String),
+    DivByZero,
+    ModByZero,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::UnknownMnemonic(m) => write!(f, "unknown mnemonic {}", m),
+            AsmError::WrongArity(m, n) => {
+                let expected = if m == "inp" { 1 } else { 2 };
+                write!(f, "{} takes {} operands, found {}", m, expected, n)
+            }
+            AsmError::BadRegister(r) => write!(f, "bad register {}", r),
+            AsmError::LiteralOutOfRange(v) => write!(f, "literal {} out of range", v),
+            AsmError::DivByZero => write!(f, "division by literal 0"),
+            AsmError::ModByZero => write!(f, "modulo by literal 0"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum AsmWarning {
+    ReadBeforeWrite(Register), // register is read while it still has initial 0
+    UnusedInput(Register),     // input is overwritten before it is read
+}
+
+impl fmt::Display for AsmWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmWarning::ReadBeforeWrite(r) => {
+                write!(f, "{} is read before it is written", r.name())
+            }
+            AsmWarning::UnusedInput(r) => {
+                write!(f, "input in {} is overwritten before it is read", r.name())
+            }
+        }
+    }
+}
+
+// error or warning with 1 based line number of source
+#[derive(Debug, PartialEq)]
+struct Diagnostic<T> {
+    line: usize,
+    kind: T,
+}
+
+impl<T: fmt::Display> fmt::Display for Diagnostic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+// returns all errors of the line, not only the first one
+fn parse_command(s: &str) -> Result<Command, Vec<AsmError>> {
     let parts: Vec<&str> = s.split_ascii_whitespace().collect();
-    match parts[..] {
-        ["inp", r] => Command::Inp(Register::from_name(r)),
-        ["add", r, a] => Command::Add(Register::from_name(r), Arg::parse(a)),
-        ["mul", r, a] => Command::Mul(Register::from_name(r), Arg::parse(a)),
-        ["div", r, a] => Command::Div(Register::from_name(r), Arg::parse(a)),
-        ["mod", r, a] => Command::Mod(Register::from_name(r), Arg::parse(a)),
-        ["eql", r, a] => Command::Eql(Register::from_name(r), Arg::parse(a)),
-        _ => panic!("cannot parse command {}", s),
+    let (mnemonic, operands) = (parts[0], &parts[1..]);
+    let arity = match mnemonic {
+        "inp" => 1,
+        "add" | "mul" | "div" | "mod" | "eql" => 2,
+        _ => return Err(vec![AsmError::UnknownMnemonic(mnemonic.to_string())]),
+    };
+    if operands.len() != arity {
+        return Err(vec![AsmError::WrongArity(
+            mnemonic.to_string(),
+            operands.len(),
+        )]);
+    }
+    let register = Register::parse(operands[0]);
+    let arg = operands.get(1).map(|a| Arg::parse(a)).transpose();
+    let mut errors = Vec::new();
+    errors.extend(register.as_ref().err().cloned());
+    errors.extend(arg.as_ref().err().cloned());
+    match (mnemonic, &arg) {
+        ("div", Ok(Some(Arg::Value(0)))) => errors.push(AsmError::DivByZero),
+        ("mod", Ok(Some(Arg::Value(0)))) => errors.push(AsmError::ModByZero),
+        _ => {}
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let r = register.unwrap();
+    Ok(match (mnemonic, arg.unwrap()) {
+        ("inp", None) => Command::Inp(r),
+        ("add", Some(a)) => Command::Add(r, a),
+        ("mul", Some(a)) => Command::Mul(r, a),
+        ("div", Some(a)) => Command::Div(r, a),
+        ("mod", Some(a)) => Command::Mod(r, a),
+        ("eql", Some(a)) => Command::Eql(r, a),
+        _ => unreachable!(),
+    })
+}
+
+// warnings about suspicious but valid programs, lines are source lines of commands
+fn lint(program: &[Command], lines: &[usize]) -> Vec<Diagnostic<AsmWarning>> {
+    let mut warnings = Vec::new();
+    let mut written = [false; 4];
+    let mut unread_input: [Option<usize>; 4] = [None; 4]; // line of inp that is not read yet
+    for (command, &line) in program.iter().zip(lines) {
+        let r = command.register();
+        let mut reads = Vec::new();
+        // inp and mul by 0 don't read previous value
+        if !matches!(command, Command::Inp(_) | Command::Mul(_, Arg::Value(0))) {
+            reads.push(r);
+        }
+        if let Some(Arg::Register(a)) = command.arg() {
+            reads.push(*a);
+        }
+        for read in reads {
+            if !written[read.0] {
+                warnings.push(Diagnostic {
+                    line,
+                    kind: AsmWarning::ReadBeforeWrite(read),
+                });
+                written[read.0] = true; // report only first read
+            }
+            unread_input[read.0] = None;
+        }
+        if let Some(inp_line) = unread_input[r.0].take() {
+            warnings.push(Diagnostic {
+                line: inp_line,
+                kind: AsmWarning::UnusedInput(r),
+            });
+        }
+        if matches!(command, Command::Inp(_)) {
+            unread_input[r.0] = Some(line);
+        }
+        written[r.0] = true;
+    }
+    warnings
+}
+
+type Assembled = (Vec<Command>, Vec<Diagnostic<AsmWarning>>);
+
+// validated parse, returns program with lint warnings or all errors found in source
+fn assemble(s: &str) -> Result<Assembled, Vec<Diagnostic<AsmError>>> {
+    let mut program = Vec::new();
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    for (i, text) in s.lines().enumerate() {
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match parse_command(text) {
+            Ok(command) => {
+                program.push(command);
+                lines.push(i + 1);
+            }
+            Err(e) => errors.extend(e.into_iter().map(|kind| Diagnostic { line: i + 1, kind })),
+        }
+    }
+    if errors.is_empty() {
+        let warnings = lint(&program, &lines);
+        Ok((program, warnings))
+    } else {
+        Err(errors)
     }
 }
 
 fn parse_program(s: &str) -> Vec<Command> {
-    s.lines()
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .map(parse_command)
-        .collect()
+    match assemble(s) {
+        Ok((program, _)) => program,
+        Err(errors) => panic!(
+            "cannot parse program\n{}",
+            errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+fn execute_abstract<T: AbstractValue>(
+    command: &Command,
+    state: &mut State<T>,
+    input: &mut dyn Iterator<Item = T>,
+) {
+    let value = match command {
+        Command::Inp(_) => input.next().unwrap(),
+        Command::Add(r, a) => state.get_register(*r).add(&state.get_arg(a)),
+        Command::Mul(r, a) => state.get_register(*r).mul(&state.get_arg(a)),
+        Command::Div(r, a) => state.get_register(*r).div(&state.get_arg(a)),
+        Command::Mod(r, a) => state.get_register(*r).modulo(&state.get_arg(a)),
+        Command::Eql(r, a) => state.get_register(*r).eql(&state.get_arg(a)),
+    };
+    state.set_register(command.register(), value);
 }
 
-fn run_program(program: &[Command], input: &[Range]) -> State {
-    let mut state: State = State::new();
-    let mut input_pos = 0;
+// abstract domain is selected by type of input values
+fn run_program<T: AbstractValue>(program: &[Command], input: &[T]) -> State<T> {
+    let mut state = State::new();
+    let mut input = input.iter().cloned();
     for command in program {
-        match command {
-            Command::Inp(r) => {
-                state.set_register(*r, input[input_pos].clone());
-                input_pos += 1;
+        execute_abstract(command, &mut state, &mut input);
+    }
+    state
+}
+
+// errors of concrete execution, every error has index of the failed instruction
+#[derive(Debug, PartialEq)]
+enum AluError {
+    DivByZero(usize),
+    ModByZero(usize),
+    ModNegative(usize),
+    NoInput(usize),
+    Overflow(usize),
+}
+
+impl error::Error for AluError {}
+
+impl fmt::Display for AluError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AluError::DivByZero(pos) => write!(f, "division by zero at instruction {}", pos),
+            AluError::ModByZero(pos) => write!(f, "modulo by zero at instruction {}", pos),
+            AluError::ModNegative(pos) => {
+                write!(f, "modulo with negative value at instruction {}", pos)
             }
-            Command::Add(r, a) => state.set_register(*r, state.get_register(*r).add(&state, a)),
-            Command::Mul(r, a) => state.set_register(*r, state.get_register(*r).mul(&state, a)),
-            Command::Div(r, a) => state.set_register(*r, state.get_register(*r).div(&state, a)),
-            Command::Mod(r, a) => state.set_register(*r, state.get_register(*r).modulo(&state, a)),
-            Command::Eql(r, a) => state.set_register(*r, state.get_register(*r).eql(&state, a)),
+            AluError::NoInput(pos) => write!(f, "no input left at instruction {}", pos),
+            AluError::Overflow(pos) => write!(f, "overflow at instruction {}", pos),
         }
     }
-    state
 }
 
-fn find_first_number_with_z_0(
-    program: &[Command],
-    digits: &[isize],
-    num_len: usize,
-) -> Option<usize> {
-    fn fun(
-        input: &mut [Range],
-        pos: usize,
-        program: &[Command],
-        digits: &[isize],
-        num_len: usize,
-    ) -> bool {
-        //println!("{:?}", input);
-        if pos == num_len {
-            return true;
+// register values for concrete execution, index is register number
+type Registers = [i64; 4];
+
+fn get_arg_value(registers: &Registers, arg: &Arg) -> i64 {
+    match arg {
+        Arg::Register(r) => registers[r.0],
+        Arg::Value(v) => *v as i64,
+    }
+}
+
+// pos is only used to report errors
+fn execute_concrete(
+    command: &Command,
+    pos: usize,
+    registers: &mut Registers,
+    input: &mut dyn Iterator<Item = i64>,
+) -> Result<(), AluError> {
+    match command {
+        Command::Inp(r) => registers[r.0] = input.next().ok_or(AluError::NoInput(pos))?,
+        Command::Add(r, a) => {
+            registers[r.0] = registers[r.0]
+                .checked_add(get_arg_value(registers, a))
+                .ok_or(AluError::Overflow(pos))?
         }
-        for digit in digits.iter() {
-            input[pos] = Range::new(*digit, *digit);
-            let z_range = run_program(program, input)
-                .get_register_by_name("z")
-                .clone();
-            let z_canbe_0 = z_range.from <= 0 && z_range.to >= 0;
-            if z_canbe_0 {
-                if fun(input, pos + 1, program, digits, num_len) {
-                    return true;
-                }
+        Command::Mul(r, a) => {
+            registers[r.0] = registers[r.0]
+                .checked_mul(get_arg_value(registers, a))
+                .ok_or(AluError::Overflow(pos))?
+        }
+        Command::Div(r, a) => {
+            let b = get_arg_value(registers, a);
+            if b == 0 {
+                return Err(AluError::DivByZero(pos));
             }
+            // rust division truncates toward zero, same as ALU
+            registers[r.0] = registers[r.0]
+                .checked_div(b)
+                .ok_or(AluError::Overflow(pos))?;
+        }
+        Command::Mod(r, a) => {
+            let b = get_arg_value(registers, a);
+            if b == 0 {
+                return Err(AluError::ModByZero(pos));
+            }
+            if registers[r.0] < 0 || b < 0 {
+                return Err(AluError::ModNegative(pos));
+            }
+            registers[r.0] %= b;
+        }
+        Command::Eql(r, a) => {
+            registers[r.0] = (registers[r.0] == get_arg_value(registers, a)) as i64
         }
-        input[pos] = Range::new(*digits.first().unwrap(), *digits.last().unwrap());
-        return false;
     }
+    Ok(())
+}
 
-    let mut input = vec![Range::new(*digits.first().unwrap(), *digits.last().unwrap()); num_len];
-    if fun(&mut input, 0, program, digits, num_len) {
-        let result = input
-            .iter()
-            .map(|range| {
-                assert_eq!(range.from, range.to);
-                range.from.to_string()
-            })
-            .collect::<String>()
-            .parse::<usize>()
-            .unwrap();
-        let z = run_program(program, &input).get_register_by_name("z").clone();
-        assert_eq!(z.from, z.to);
-        assert_eq!(z.from, 0);
+fn run_program_concrete(program: &[Command], input: &[i64]) -> Result<Registers, AluError> {
+    let mut registers = [0; 4];
+    let mut input = input.iter().cloned();
+    for (pos, command) in program.iter().enumerate() {
+        execute_concrete(command, pos, &mut registers, &mut input)?;
+    }
+    Ok(registers)
+}
 
-        Some(result)
-    } else {
-        None
+// register values known without running the program
+type KnownRegisters = [Option<i64>; 4];
+
+// replaces registers with known values by literals and removes instructions that don't change registers
+fn propagate_constants(program: &[Command]) -> Vec<Command> {
+    let mut known: KnownRegisters = [Some(0); 4];
+    let mut result = Vec::new();
+    for command in program {
+        let r = command.register();
+        let command = match command.arg() {
+            None => {
+                known[r.0] = None;
+                result.push(command.clone());
+                continue;
+            }
+            Some(Arg::Register(a)) => match known[a.0] {
+                Some(v) => command.with_arg(Arg::Value(v as isize)),
+                None => command.clone(),
+            },
+            Some(Arg::Value(_)) => command.clone(),
+        };
+        let value = match (known[r.0], command.arg()) {
+            (Some(lhs), Some(Arg::Value(_))) => {
+                let mut registers = [0; 4];
+                registers[r.0] = lhs;
+                match execute_concrete(&command, 0, &mut registers, &mut std::iter::empty()) {
+                    Ok(()) => Some(registers[r.0]),
+                    Err(_) => None, // keep the fault
+                }
+            }
+            _ => match command {
+                Command::Mul(_, Arg::Value(0)) => Some(0),
+                _ => None,
+            },
+        };
+        let is_identity = matches!(
+            command,
+            Command::Add(_, Arg::Value(0))
+                | Command::Mul(_, Arg::Value(1))
+                | Command::Div(_, Arg::Value(1))
+        );
+        if is_identity || (value.is_some() && value == known[r.0]) {
+            continue;
+        }
+        known[r.0] = value;
+        result.push(command);
     }
+    result
 }
 
-pub fn main() {
-    let input = parse_input(&std::fs::read_to_string("input/day24.txt").unwrap());
+// removes instructions whose results never reach output registers
+fn remove_dead_code(program: &[Command], outputs: &[Register]) -> Vec<Command> {
+    let mut live = [false; 4];
+    for r in outputs {
+        live[r.0] = true;
+    }
+    let mut result = Vec::new();
+    for command in program.iter().rev() {
+        let r = command.register();
+        let is_inp = matches!(command, Command::Inp(_));
+        if !is_inp && !live[r.0] && !command.can_fail() {
+            continue;
+        }
+        // inp and mul by 0 don't depend on previous value of register
+        live[r.0] = !is_inp && !matches!(command, Command::Mul(_, Arg::Value(0)));
+        if let Some(Arg::Register(a)) = command.arg() {
+            live[a.0] = true;
+        }
+        result.push(command.clone());
+    }
+    result.reverse();
+    result
+}
+
+// shorter program with the same values of output registers
+fn optimize(program: &[Command], outputs: &[Register]) -> Vec<Command> {
+    let mut program = program.to_vec();
+    loop {
+        let optimized = remove_dead_code(&propagate_constants(&program), outputs);
+        if optimized.len() == program.len() {
+            return optimized;
+        }
+        program = optimized;
+    }
+}
+
+fn program_to_string(program: &[Command]) -> String {
+    program.iter().map(|c| c.to_string() + "\n").collect()
+}
+
+// runs both programs on random inputs and compares output registers,
+// programs are also equivalent on inputs where both of them fail
+fn check_equivalent(
+    a: &[Command],
+    b: &[Command],
+    outputs: &[Register],
+    values: RangeInclusive<i64>,
+    tries: usize,
+    rng: &mut StdRng,
+) -> Result<(), String> {
+    let input_len = a.iter().filter(|c| matches!(c, Command::Inp(_))).count();
+    for _ in 0..tries {
+        let input = (0..input_len)
+            .map(|_| rng.gen_range(values.clone()))
+            .collect::<Vec<_>>();
+        let equal = match (
+            run_program_concrete(a, &input),
+            run_program_concrete(b, &input),
+        ) {
+            (Ok(ra), Ok(rb)) => outputs.iter().all(|r| ra[r.0] == rb[r.0]),
+            (Err(_), Err(_)) => true,
+            _ => false,
+        };
+        if !equal {
+            return Err(format!(
+                "input {:?}: {:?} and {:?}",
+                input,
+                run_program_concrete(a, &input),
+                run_program_concrete(b, &input)
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn number_to_digits(number: usize) -> Vec<i64> {
+    number
+        .to_string()
+        .chars()
+        .map(|c| c.to_digit(10).unwrap() as i64)
+        .collect()
+}
+
+fn is_valid_model_number(program: &[Command], number: usize) -> Result<bool, AluError> {
+    let digits = number_to_digits(number);
+    if digits.contains(&0) {
+        return Ok(false);
+    }
+    let registers = run_program_concrete(program, &digits)?;
+    Ok(registers[Register::from_name("z").0] == 0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Breakpoint {
+    Instruction(usize), // stop before instruction with this index
+    Inp,                // stop before every inp instruction
+}
+
+#[derive(Debug, PartialEq)]
+enum StopReason {
+    Breakpoint(usize), // index of the next instruction
+    Finished,
+}
+
+// registers and not yet consumed input of the program being debugged
+enum Machine<'a> {
+    Concrete(Registers, std::iter::Cloned<std::slice::Iter<'a, i64>>),
+    Ranges(State, std::iter::Cloned<std::slice::Iter<'a, Range>>),
+}
+
+impl Machine<'_> {
+    fn format_registers(&self) -> String {
+        match self {
+            Machine::Concrete(registers, _) => format!(
+                "w={} x={} y={} z={}",
+                registers[0], registers[1], registers[2], registers[3]
+            ),
+            Machine::Ranges(state, _) => format!(
+                "w={} x={} y={} z={}",
+                state.0[0], state.0[1], state.0[2], state.0[3]
+            ),
+        }
+    }
+}
+
+// executes program instruction by instruction and records registers after every instruction,
+// with echo every trace line is also written out as soon as the instruction is executed
+struct Debugger<'a> {
+    program: &'a [Command],
+    pos: usize, // index of the next instruction
+    machine: Machine<'a>,
+    breakpoints: Vec<Breakpoint>,
+    trace: Vec<String>,
+    stopped_at: Option<usize>, // breakpoint position run stopped at, skipped when run continues
+    echo: Option<&'a mut dyn io::Write>,
+}
+
+impl<'a> Debugger<'a> {
+    fn new(program: &'a [Command], machine: Machine<'a>) -> Debugger<'a> {
+        Debugger {
+            program,
+            pos: 0,
+            machine,
+            breakpoints: Vec::new(),
+            trace: Vec::new(),
+            stopped_at: None,
+            echo: None,
+        }
+    }
+
+    fn concrete(program: &'a [Command], input: &'a [i64]) -> Debugger<'a> {
+        Self::new(program, Machine::Concrete([0; 4], input.iter().cloned()))
+    }
+
+    fn ranges(program: &'a [Command], input: &'a [Range]) -> Debugger<'a> {
+        Self::new(
+            program,
+            Machine::Ranges(State::new(), input.iter().cloned()),
+        )
+    }
+
+    fn with_echo(mut self, out: &'a mut dyn io::Write) -> Debugger<'a> {
+        self.echo = Some(out);
+        self
+    }
+
+    fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.pos >= self.program.len()
+    }
+
+    fn is_at_breakpoint(&self) -> bool {
+        self.breakpoints.iter().any(|breakpoint| match breakpoint {
+            Breakpoint::Instruction(pos) => *pos == self.pos,
+            Breakpoint::Inp => matches!(self.program[self.pos], Command::Inp(_)),
+        })
+    }
+
+    // executes one instruction, returns false if program is already finished
+    fn step(&mut self) -> Result<bool, AluError> {
+        if self.is_finished() {
+            return Ok(false);
+        }
+        let command = &self.program[self.pos];
+        match &mut self.machine {
+            Machine::Concrete(registers, input) => {
+                execute_concrete(command, self.pos, registers, input)?
+            }
+            Machine::Ranges(state, input) => execute_abstract(command, state, input),
+        }
+        let line = format!(
+            "{:4} {:10} {}",
+            self.pos,
+            command.to_string(),
+            self.machine.format_registers()
+        );
+        if let Some(out) = &mut self.echo {
+            writeln!(out, "{}", line).expect("failed to write debugger echo");
+        }
+        self.trace.push(line);
+        self.pos += 1;
+        self.stopped_at = None;
+        Ok(true)
+    }
+
+    // executes instructions until breakpoint or end of program, stops before instruction
+    // with breakpoint, calling run again continues from the breakpoint it stopped at
+    fn run(&mut self) -> Result<StopReason, AluError> {
+        while !self.is_finished() {
+            if self.stopped_at != Some(self.pos) && self.is_at_breakpoint() {
+                self.stopped_at = Some(self.pos);
+                return Ok(StopReason::Breakpoint(self.pos));
+            }
+            self.step()?;
+        }
+        Ok(StopReason::Finished)
+    }
+
+    // one line per executed instruction, traces of two runs can be compared with diff
+    fn dump_trace(&self, filename: &Path) -> io::Result<()> {
+        std::fs::write(filename, self.trace.join("\n") + "\n")
+    }
+}
+
+// instructions whose literals are MONAD parameters, in the order of BlockParams fields
+const MONAD_PARAMS: [&str; 3] = ["div z", "add x", "add y"];
+
+// z values blocks are checked on, including values around multiples of 26
+const MONAD_CHECK_Z: [i64; 7] = [0, 1, 25, 26, 27, 26 * 26 + 5, 123456];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BlockParams {
+    div_z: isize,
+    add_x: isize,
+    add_y: isize,
+}
+
+impl BlockParams {
+    // z after block with digit w, same as running block with concrete interpreter
+    fn eval(&self, z: i64, w: i64) -> i64 {
+        let x = z % 26 + self.add_x as i64 != w;
+        let z = z / self.div_z as i64;
+        if x {
+            z * 26 + w + self.add_y as i64
+        } else {
+            z
+        }
+    }
+
+    fn pseudo_code(&self, digit: usize) -> String {
+        let mut result = format!("// digit {}\n", digit);
+        result += &format!("w = d[{}]\n", digit);
+        result += &format!("x = z % 26 + {} != w\n", self.add_x);
+        if self.div_z != 1 {
+            result += &format!("z = z / {}\n", self.div_z);
+        }
+        result += &format!("if x {{ z = z * 26 + w + {} }}\n", self.add_y);
+        result
+    }
+}
+
+// splits program into parts that start with inp
+fn split_blocks(program: &[Command]) -> Vec<&[Command]> {
+    let mut starts = program
+        .iter()
+        .enumerate()
+        .filter(|(_, command)| matches!(command, Command::Inp(_)))
+        .map(|(pos, _)| pos)
+        .collect::<Vec<_>>();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(program.len());
+    starts.windows(2).map(|w| &program[w[0]..w[1]]).collect()
+}
+
+// instructions shared by all blocks of a program, literals that differ between blocks are
+// block parameters
+#[derive(Debug)]
+struct BlockTemplate {
+    commands: Vec<Command>,
+    params: Vec<usize>, // positions of instructions with parameter literal
+}
+
+impl BlockTemplate {
+    fn values(&self, block: &[Command]) -> Vec<isize> {
+        self.params
+            .iter()
+            .map(|&pos| literal(&block[pos]).unwrap())
+            .collect()
+    }
+}
+
+impl fmt::Display for BlockTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (pos, command) in self.commands.iter().enumerate() {
+            if pos > 0 {
+                writeln!(f)?;
+            }
+            if self.params.contains(&pos) {
+                let text = command.to_string();
+                write!(f, "{} _", text.rsplit_once(' ').unwrap().0)?;
+            } else {
+                write!(f, "{}", command)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn literal(command: &Command) -> Option<isize> {
+    match command.arg() {
+        Some(Arg::Value(v)) => Some(*v),
+        _ => None,
+    }
+}
+
+// compares blocks instruction by instruction, instructions that differ only by literal
+// become parameters, any other difference is an error
+fn detect_template(blocks: &[&[Command]]) -> Result<BlockTemplate, String> {
+    let first = blocks[0];
+    for (i, block) in blocks.iter().enumerate() {
+        if block.len() != first.len() {
+            return Err(format!(
+                "block {}: block has {} instructions instead of {}",
+                i,
+                block.len(),
+                first.len()
+            ));
+        }
+    }
+    let mut params = Vec::new();
+    for (pos, command) in first.iter().enumerate() {
+        let text = command.to_string();
+        if blocks.iter().all(|block| block[pos].to_string() == text) {
+            continue;
+        }
+        let shape = command.with_arg(Arg::Value(0)).to_string();
+        for (i, block) in blocks.iter().enumerate() {
+            if block[pos].to_string() == text {
+                continue;
+            }
+            if literal(command).is_none()
+                || literal(&block[pos]).is_none()
+                || block[pos].with_arg(Arg::Value(0)).to_string() != shape
+            {
+                return Err(format!(
+                    "block {}: {} does not match {} of block 0",
+                    i, block[pos], text
+                ));
+            }
+        }
+        params.push(pos);
+    }
+    Ok(BlockTemplate {
+        commands: first.to_vec(),
+        params,
+    })
+}
+
+// z after running block with digit w from z, other registers start from 0
+fn run_block_concrete(block: &[Command], z: i64, w: i64) -> Result<i64, AluError> {
+    let mut registers = [0, 0, 0, z];
+    let mut input = std::iter::once(w);
+    for (pos, command) in block.iter().enumerate() {
+        execute_concrete(command, pos, &mut registers, &mut input)?;
+    }
+    Ok(registers[3])
+}
+
+// extracts parameters of every block from the detected template, every MONAD parameter has to be
+// a template parameter and blocks evaluated with the parameters have to agree with the interpreter
+fn decompile(program: &[Command]) -> Result<Vec<BlockParams>, String> {
+    let blocks = split_blocks(program);
+    let template = detect_template(&blocks)?;
+    let not_monad = || format!("block structure is not MONAD:\n{}", template);
+    if template.params.len() != MONAD_PARAMS.len() {
+        return Err(not_monad());
+    }
+    // index in template parameters of every MONAD parameter
+    let mut order = Vec::new();
+    for name in MONAD_PARAMS {
+        let shape = format!("{} 0", name);
+        match template
+            .params
+            .iter()
+            .position(|&pos| template.commands[pos].with_arg(Arg::Value(0)).to_string() == shape)
+        {
+            Some(i) => order.push(i),
+            None => return Err(not_monad()),
+        }
+    }
+    let mut result = Vec::new();
+    for (i, block) in blocks.iter().enumerate() {
+        let values = template.values(block);
+        let params = BlockParams {
+            div_z: values[order[0]],
+            add_x: values[order[1]],
+            add_y: values[order[2]],
+        };
+        for z in MONAD_CHECK_Z {
+            for w in 1..=9 {
+                let expected = run_block_concrete(block, z, w)
+                    .map_err(|e| format!("block {}: z={} w={}: {}", i, z, w, e))?;
+                // division by zero is already reported by the interpreter
+                let actual = params.eval(z, w);
+                if actual != expected {
+                    return Err(format!(
+                        "block {}: z={} w={} gives z={} but {:?} gives z={}",
+                        i, z, w, expected, params, actual
+                    ));
+                }
+            }
+        }
+        result.push(params);
+    }
+    Ok(result)
+}
+
+fn to_pseudo_code(blocks: &[BlockParams]) -> String {
+    let mut result = String::from("z = 0\n");
+    for (digit, block) in blocks.iter().enumerate() {
+        result += &block.pseudo_code(digit);
+    }
+    result
+}
+
+// recurrence z[i + 1] = f(z[i], d[i]), one line per block
+fn to_recurrence(blocks: &[BlockParams]) -> String {
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            format!(
+                "z{} = if z{} % 26 + {} == d{} {{ z{} / {} }} else {{ z{} / {} * 26 + d{} + {} }}\n",
+                i + 1,
+                i,
+                b.add_x,
+                i,
+                i,
+                b.div_z,
+                i,
+                b.div_z,
+                i,
+                b.add_y
+            )
+        })
+        .collect()
+}
+
+fn run_blocks(blocks: &[BlockParams], digits: &[i64]) -> i64 {
+    blocks
+        .iter()
+        .zip(digits)
+        .fold(0, |z, (block, &w)| block.eval(z, w))
+}
+
+// z works as a stack of base 26 digits: block with div z 1 always pushes d[i] + add_y
+// (add_x is at least 10 so x is never equal to digit), block with div z 26 pops top value
+// and pushes again unless d[i] == top + add_x. z is 0 at the end only if every pop block matches,
+// which gives constraint d[pop] = d[push] + add_y[push] + add_x[pop] for every pair of blocks
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DigitConstraint {
+    push: usize,
+    pop: usize,
+    offset: i64,
+}
+
+impl DigitConstraint {
+    // digits allowed at push position so that digit at pop position is also in 1..=9
+    fn push_digits(&self) -> std::ops::RangeInclusive<i64> {
+        1.max(1 - self.offset)..=9.min(9 - self.offset)
+    }
+}
+
+#[derive(Debug)]
+struct ModelNumbers {
+    len: usize,
+    constraints: Vec<DigitConstraint>, // sorted by push position
+}
+
+impl ModelNumbers {
+    fn from_blocks(blocks: &[BlockParams]) -> Result<ModelNumbers, String> {
+        let mut stack = Vec::new();
+        let mut constraints = Vec::new();
+        for (i, block) in blocks.iter().enumerate() {
+            match block.div_z {
+                1 if block.add_x >= 10 => stack.push(i),
+                1 => {
+                    return Err(format!(
+                        "block {}: push block with add x {}",
+                        i, block.add_x
+                    ))
+                }
+                26 => {
+                    let push = stack
+                        .pop()
+                        .ok_or(format!("block {}: pop from empty stack", i))?;
+                    constraints.push(DigitConstraint {
+                        push,
+                        pop: i,
+                        offset: (blocks[push].add_y + block.add_x) as i64,
+                    });
+                }
+                div => return Err(format!("block {}: unexpected div z {}", i, div)),
+            }
+        }
+        if !stack.is_empty() {
+            return Err(format!("blocks {:?} are never popped", stack));
+        }
+        constraints.sort_by_key(|c| c.push);
+        Ok(ModelNumbers {
+            len: blocks.len(),
+            constraints,
+        })
+    }
+
+    fn count(&self) -> usize {
+        self.constraints
+            .iter()
+            .map(|c| c.push_digits().count())
+            .product()
+    }
+
+    fn make_number(&self, push_digits: &[i64]) -> usize {
+        let mut digits = vec![0; self.len];
+        for (c, &digit) in self.constraints.iter().zip(push_digits) {
+            digits[c.push] = digit;
+            digits[c.pop] = digit + c.offset;
+        }
+        digits.iter().fold(0, |acc, &d| acc * 10 + d as usize)
+    }
+
+    fn largest(&self) -> Option<usize> {
+        self.iter_from(|c| *c.push_digits().end()).next()
+    }
+
+    fn smallest(&self) -> Option<usize> {
+        self.iter().next()
+    }
+
+    fn iter_from<F>(&self, first: F) -> impl Iterator<Item = usize> + '_
+    where
+        F: Fn(&DigitConstraint) -> i64,
+    {
+        let start = if self.count() > 0 {
+            Some(self.constraints.iter().map(first).collect::<Vec<_>>())
+        } else {
+            None
+        };
+        std::iter::successors(start, move |push_digits| {
+            // increment last push digit, carry to previous ones
+            let mut next = push_digits.clone();
+            for (i, c) in self.constraints.iter().enumerate().rev() {
+                if next[i] < *c.push_digits().end() {
+                    next[i] += 1;
+                    return Some(next);
+                }
+                next[i] = *c.push_digits().start();
+            }
+            None
+        })
+        .map(move |push_digits| self.make_number(&push_digits))
+    }
+
+    // all valid numbers in ascending order, push positions go before their pop positions
+    // so ordering by push digits is the same as ordering numbers
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter_from(|c| *c.push_digits().start())
+    }
+}
+
+fn find_first_number_with_z_0<T: AbstractValue>(
+    program: &[Command],
+    digits: &[isize],
+    num_len: usize,
+) -> Option<usize> {
+    fn fun<T: AbstractValue>(
+        input: &mut [T],
+        pos: usize,
+        program: &[Command],
+        digits: &[isize],
+        num_len: usize,
+    ) -> bool {
+        //println!("{:?}", input);
+        if pos == num_len {
+            return true;
+        }
+        for digit in digits.iter() {
+            input[pos] = T::from_values(&[*digit]);
+            let z_canbe_0 = run_program(program, input)
+                .get_register_by_name("z")
+                .contains(0);
+            if z_canbe_0 && fun(input, pos + 1, program, digits, num_len) {
+                return true;
+            }
+        }
+        input[pos] = T::from_values(digits);
+        false
+    }
+
+    let mut input = vec![T::from_values(digits); num_len];
+    if fun(&mut input, 0, program, digits, num_len) {
+        let result = input
+            .iter()
+            .map(|value| value.as_constant().unwrap().to_string())
+            .collect::<String>()
+            .parse::<usize>()
+            .unwrap();
+        let z = run_program(program, &input)
+            .get_register_by_name("z")
+            .clone();
+        assert_eq!(z.as_constant(), Some(0));
+
+        Some(result)
+    } else {
+        None
+    }
+}
+
+pub fn main() {
+    let input = parse_input(&std::fs::read_to_string("input/day24.txt").unwrap());
     assert_eq!(
         run_program(input.get("negate").unwrap(), &mut vec![Range::new(10, 10)])
             .get_register_by_name("x"),
@@ -338,23 +1605,580 @@ pub fn main() {
             Range::new(1, 1)
         ])
     );
+    test_concrete(&input);
+    test_debugger(&input);
+    test_decompile(&input);
+    test_model_numbers(&input);
+    test_soundness::<Range>();
+    test_soundness::<ValueSet>();
+    test_value_set(&input);
+    test_optimize(&input);
+    test_assemble();
     println!("tests ok");
-    println!(
-        "day 24 pt1 {:?}",
-        find_first_number_with_z_0(
-            input.get("day24").unwrap(),
-            &vec![9, 8, 7, 6, 5, 4, 3, 2, 1],
-            14,
-        )
+
+    let day24_program = input.get("day24").unwrap();
+    let pt1 = find_first_number_with_z_0::<Range>(day24_program, &[9, 8, 7, 6, 5, 4, 3, 2, 1], 14);
+    assert_eq!(
+        pt1.map(|n| is_valid_model_number(day24_program, n)),
+        Some(Ok(true))
+    );
+    print!("{}", to_recurrence(&decompile(day24_program).unwrap()));
+    println!("day 24 pt1 {:?}", pt1);
+    let pt2 = find_first_number_with_z_0::<Range>(day24_program, &[1, 2, 3, 4, 5, 6, 7, 8, 9], 14);
+    assert_eq!(
+        pt2.map(|n| is_valid_model_number(day24_program, n)),
+        Some(Ok(true))
+    );
+    println!("day 24 pt2 {:?}", pt2);
+
+    let model_numbers = ModelNumbers::from_blocks(&decompile(day24_program).unwrap()).unwrap();
+    assert_eq!(model_numbers.largest(), pt1);
+    assert_eq!(model_numbers.smallest(), pt2);
+    println!("day 24 valid model numbers {}", model_numbers.count());
+
+    let optimized = optimize(day24_program, &[Register::from_name("z")]);
+    assert_eq!(
+        pt1.map(|n| is_valid_model_number(&optimized, n)),
+        Some(Ok(true))
     );
     println!(
-        "day 24 pt2 {:?}",
-        find_first_number_with_z_0(
-            input.get("day24").unwrap(),
-            &vec![1, 2, 3, 4, 5, 6, 7, 8, 9],
-            14,
+        "day 24 optimized program {} -> {} instructions",
+        day24_program.len(),
+        optimized.len()
+    );
+
+    let mut stdout = io::stdout();
+    let mut debugger =
+        Debugger::concrete(input.get("negate").unwrap(), &[10]).with_echo(&mut stdout);
+    println!("day 24 negate trace");
+    assert_eq!(debugger.run(), Ok(StopReason::Finished));
+
+    // traces of both answers can be compared with diff
+    for (name, number) in [("pt1", pt1.unwrap()), ("pt2", pt2.unwrap())] {
+        let digits = number_to_digits(number);
+        let mut debugger = Debugger::concrete(day24_program, &digits);
+        assert_eq!(debugger.run(), Ok(StopReason::Finished));
+        let filename = std::env::temp_dir().join(format!("day24_{}_trace.txt", name));
+        debugger.dump_trace(&filename).unwrap();
+        println!("day 24 {} trace {}", name, filename.display());
+    }
+}
+
+fn test_decompile(input: &HashMap<String, Vec<Command>>) {
+    let get_bits = input.get("get_bits").unwrap();
+    assert_eq!(split_blocks(get_bits).len(), 1);
+    assert_eq!(
+        decompile(get_bits).unwrap_err(),
+        format!(
+            "block structure is not MONAD:\n{}",
+            program_to_string(get_bits).trim_end()
         )
     );
+
+    // structure is found for blocks of any shape
+    let program = parse_program("inp w\nadd z w\nmul z 3\ninp w\nadd z w\nmul z 5");
+    let blocks = split_blocks(&program);
+    let template = detect_template(&blocks).unwrap();
+    assert_eq!(template.to_string(), "inp w\nadd z w\nmul z _");
+    assert_eq!(template.values(blocks[1]), vec![5]);
+    assert!(decompile(&program)
+        .unwrap_err()
+        .starts_with("block structure is not MONAD"));
+    let program = parse_program("inp w\nadd z w\ninp w\nadd z x");
+    assert_eq!(
+        detect_template(&split_blocks(&program)).unwrap_err(),
+        "block 1: add z x does not match add z w of block 0"
+    );
+
+    let day24 = input.get("day24").unwrap();
+    assert_eq!(split_blocks(day24).len(), 14);
+    let template = detect_template(&split_blocks(day24)).unwrap();
+    assert_eq!(
+        template.to_string(),
+        "inp w\nmul x 0\nadd x z\nmod x 26\ndiv z _\nadd x _\neql x w\neql x 0\nmul y 0\n\
+         add y 25\nmul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y _\nmul y x\nadd z y"
+    );
+    let blocks = decompile(day24).unwrap();
+    assert_eq!(
+        blocks[0],
+        BlockParams {
+            div_z: 1,
+            add_x: 14,
+            add_y: 0
+        }
+    );
+    let pseudo_code = to_pseudo_code(&blocks);
+    assert!(pseudo_code.starts_with("z = 0\n// digit 0\nw = d[0]\nx = z % 26 + 14 != w\n"));
+    assert_eq!(to_recurrence(&blocks).lines().count(), 14);
+
+    // blocks evaluated directly agree with the interpreter
+    for number in [
+        91297395919993,
+        71131151917891,
+        11111111111111,
+        99999999999999,
+        13579246899999,
+    ] {
+        let digits = number_to_digits(number);
+        let z = run_program_concrete(day24, &digits).unwrap()[3];
+        assert_eq!(run_blocks(&blocks, &digits), z, "{}", number);
+    }
+
+    // parameters are found by their instructions, independent instructions can be reordered
+    let mut reordered = day24.clone();
+    for block in reordered.chunks_mut(18) {
+        block.swap(4, 5);
+    }
+    assert_eq!(decompile(&reordered), Ok(blocks.clone()));
+    // same shape that computes something else is not accepted
+    let mut changed = day24.clone();
+    for block in changed.chunks_mut(18) {
+        block[3] = Command::Mod(Register(1), Arg::Value(25));
+    }
+    assert_eq!(
+        decompile(&changed),
+        Err("block 4: z=681 w=3 gives z=682 \
+             but BlockParams { div_z: 26, add_x: -2, add_y: 3 } gives z=26"
+            .to_string())
+    );
+
+    // changed parameter is reported with its block
+    let mut changed = day24.clone();
+    changed[18 + 4] = Command::Mul(Register(3), Arg::Value(26));
+    assert_eq!(
+        decompile(&changed),
+        Err("block 1: mul z 26 does not match div z 1 of block 0".to_string())
+    );
+}
+
+// every concrete result of operation must be inside of abstract result
+fn test_soundness<T: AbstractValue>() {
+    let families: Vec<Vec<isize>> = vec![
+        vec![0],
+        vec![1],
+        vec![-1],
+        vec![26],
+        vec![-5, 0, 7],
+        vec![-13, 0, 13, 26],
+        (1..=9).collect(),
+        (0..40).step_by(4).collect(),
+        (0..1000).step_by(3).collect(),
+        (-100..100).collect(),
+        (-260..0).step_by(26).collect(),
+    ];
+    let (x, y) = (Register(1), Register(2));
+    let commands = [
+        Command::Add(x, Arg::Register(y)),
+        Command::Mul(x, Arg::Register(y)),
+        Command::Div(x, Arg::Register(y)),
+        Command::Mod(x, Arg::Register(y)),
+        Command::Eql(x, Arg::Register(y)),
+    ];
+    for a in families.iter() {
+        for b in families.iter() {
+            for command in commands.iter() {
+                let mut state = State::<T>::new();
+                state.set_register(x, T::from_values(a));
+                state.set_register(y, T::from_values(b));
+                execute_abstract(command, &mut state, &mut std::iter::empty());
+                let result = state.get_register(x);
+                for &va in a {
+                    for &vb in b {
+                        let mut registers = [0, va as i64, vb as i64, 0];
+                        let ok =
+                            execute_concrete(command, 0, &mut registers, &mut std::iter::empty());
+                        if ok.is_ok() {
+                            assert!(
+                                result.contains(registers[1] as isize),
+                                "{} {} {} -> {} does not contain {}",
+                                command,
+                                va,
+                                vb,
+                                result,
+                                registers[1]
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn test_value_set(input: &HashMap<String, Vec<Command>>) {
+    // small sets are exact
+    let digits = ValueSet::from_values(&[1, 5, 9]);
+    assert_eq!(
+        digits.mul(&digits),
+        ValueSet::from_values(&[1, 5, 9, 25, 45, 81])
+    );
+    assert_eq!(digits.to_string(), "{1,5,9}");
+    // large sets keep step, range would be 0..=25
+    let big = ValueSet::from_values(&(0..1000).step_by(13).collect::<Vec<_>>());
+    assert_eq!(big.to_string(), "[0..988 step 13]");
+    assert_eq!(
+        big.modulo(&ValueSet::from_values(&[13])),
+        ValueSet::from_values(&[0])
+    );
+    assert_eq!(
+        big.eql(&ValueSet::from_values(&[14])),
+        ValueSet::from_values(&[0])
+    );
+    assert_eq!(
+        big.div(&ValueSet::from_values(&[13])),
+        ValueSet::from_strided(Strided::new(0, 76, 1))
+    );
+    // division by zero and mod of negative value fail in concrete interpreter and are skipped
+    let zero = ValueSet::from_values(&[0]);
+    assert_eq!(digits.div(&zero), ValueSet::Set(BTreeSet::new()));
+    assert!(ValueSet::from_values(&[-3])
+        .modulo(&digits)
+        .add(&digits)
+        .is_empty());
+
+    // MONAD on all inputs contains z of concrete runs
+    let day24 = input.get("day24").unwrap();
+    let any_digit = vec![ValueSet::from_values(&(1..=9).collect::<Vec<_>>()); 14];
+    let z = run_program(day24, &any_digit)
+        .get_register_by_name("z")
+        .clone();
+    for number in [
+        91297395919993,
+        71131151917891,
+        11111111111111,
+        99999999999999,
+    ] {
+        let concrete_z = run_program_concrete(day24, &number_to_digits(number)).unwrap()[3];
+        assert!(z.contains(concrete_z as isize));
+    }
+    // with all but last digit fixed value set knows that z can't be 1..=12, range doesn't
+    let digits = number_to_digits(91297395919993);
+    let mut value_sets = any_digit;
+    let mut ranges = vec![Range::new(1, 9); 14];
+    for i in 0..13 {
+        value_sets[i] = ValueSet::from_values(&[digits[i] as isize]);
+        ranges[i] = Range::new(digits[i] as isize, digits[i] as isize);
+    }
+    let z = run_program(day24, &value_sets)
+        .get_register_by_name("z")
+        .clone();
+    let range_z = *run_program(day24, &ranges).get_register_by_name("z");
+    assert_eq!(z.to_string(), "{0,13,14,15,16,17,18,19,20,21}");
+    assert_eq!(range_z, Range::new(0, 21));
+}
+
+fn test_assemble() {
+    let errors = assemble(
+        "inp w
+        nop x
+
+        add x
+        mul q 2
+        div x 0
+        mod x 0
+        eql 3 99999999999999999999
+        add x w y",
+    )
+    .unwrap_err();
+    let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "line 2: unknown mnemonic nop",
+            "line 4: add takes 2 operands, found 1",
+            "line 5: bad register q",
+            "line 6: division by literal 0",
+            "line 7: modulo by literal 0",
+            "line 8: bad register 3",
+            "line 8: literal 99999999999999999999 out of range",
+            "line 9: add takes 2 operands, found 3",
+        ]
+    );
+    assert_eq!(errors[2].kind, AsmError::BadRegister("q".to_string()));
+
+    let (program, warnings) = assemble("inp w\ninp w\nadd z w\nmul x 0\nadd x y").unwrap();
+    assert_eq!(program.len(), 5);
+    let messages = warnings.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "line 1: input in w is overwritten before it is read",
+            "line 3: z is read before it is written",
+            "line 5: y is read before it is written",
+        ]
+    );
+
+    let source = std::fs::read_to_string("input/day24.txt").unwrap();
+    let day24 = source
+        .split("\n\n")
+        .find(|s| s.starts_with("day24"))
+        .unwrap();
+    let (program, warnings) = assemble(day24.split_once('\n').unwrap().1).unwrap();
+    assert_eq!(program.len(), 252);
+    // first block adds z to x while z is still 0
+    assert_eq!(
+        warnings,
+        vec![Diagnostic {
+            line: 3,
+            kind: AsmWarning::ReadBeforeWrite(Register(3))
+        }]
+    );
+}
+
+fn test_optimize(input: &HashMap<String, Vec<Command>>) {
+    let z = [Register::from_name("z")];
+    let program = parse_program(
+        "inp w
+        mul x 0
+        add x w
+        div x 1
+        add y 3
+        mul y 2
+        add z y",
+    );
+    assert_eq!(
+        program_to_string(&optimize(&program, &z)),
+        "inp w\nadd z 6\n"
+    );
+    // overflowing instruction is not folded
+    let program = parse_program("add z 4611686018427387904\nmul z 2");
+    assert_eq!(
+        program_to_string(&optimize(&program, &z)),
+        "add z 4611686018427387904\nmul z 2\n"
+    );
+    // x is not needed for z, but division by w can fail
+    let program = parse_program("inp w\ndiv x w\nadd z 1\nadd x 1");
+    assert_eq!(
+        program_to_string(&optimize(&program, &z)),
+        "inp w\ndiv x w\nadd z 1\n"
+    );
+
+    let mut rng = StdRng::seed_from_u64(24);
+    let all = [0, 1, 2, 3].map(Register);
+    for name in ["negate", "is_3_times_bigger", "get_bits"] {
+        let program = input.get(name).unwrap();
+        let optimized = optimize(program, &all);
+        assert!(optimized.len() <= program.len());
+        assert_eq!(
+            check_equivalent(program, &optimized, &all, -20..=20, 1000, &mut rng),
+            Ok(())
+        );
+    }
+
+    let day24 = input.get("day24").unwrap();
+    let optimized = optimize(day24, &z);
+    assert_eq!(optimize(&optimized, &z).len(), optimized.len());
+    assert!(!optimized
+        .iter()
+        .any(|c| matches!(c, Command::Div(_, Arg::Value(1)))));
+    for values in [1..=9, -50..=50] {
+        assert_eq!(
+            check_equivalent(day24, &optimized, &z, values, 1000, &mut rng),
+            Ok(())
+        );
+    }
+    // broken program is detected
+    let mut broken = optimized;
+    broken.pop();
+    assert!(check_equivalent(day24, &broken, &z, 1..=9, 1000, &mut rng).is_err());
+}
+
+fn test_model_numbers(input: &HashMap<String, Vec<Command>>) {
+    let day24 = input.get("day24").unwrap();
+    let blocks = decompile(day24).unwrap();
+    let model_numbers = ModelNumbers::from_blocks(&blocks).unwrap();
+    assert_eq!(model_numbers.constraints.len(), 7);
+    assert_eq!(
+        model_numbers.constraints[0],
+        DigitConstraint {
+            push: 0,
+            pop: 13,
+            offset: -6
+        }
+    );
+    let largest = model_numbers.largest().unwrap();
+    let smallest = model_numbers.smallest().unwrap();
+    assert_eq!(is_valid_model_number(day24, largest), Ok(true));
+    assert_eq!(is_valid_model_number(day24, smallest), Ok(true));
+
+    // every number is valid, checked on decompiled blocks and with the interpreter
+    let mut count = 0;
+    let mut prev = 0;
+    for number in model_numbers.iter() {
+        assert!(number > prev);
+        assert_eq!(
+            run_blocks(&blocks, &number_to_digits(number)),
+            0,
+            "{}",
+            number
+        );
+        assert_eq!(is_valid_model_number(day24, number), Ok(true));
+        prev = number;
+        count += 1;
+    }
+    assert_eq!(count, model_numbers.count());
+    assert_eq!(prev, largest);
+
+    // numbers next to valid ones are not valid
+    assert_eq!(is_valid_model_number(day24, largest - 1), Ok(false));
+    assert_eq!(is_valid_model_number(day24, smallest + 1), Ok(false));
+
+    // constraint that can't be met leaves no numbers
+    let mut blocks = blocks;
+    blocks[13].add_x = -20;
+    let model_numbers = ModelNumbers::from_blocks(&blocks).unwrap();
+    assert_eq!(model_numbers.count(), 0);
+    assert_eq!(model_numbers.largest(), None);
+    assert_eq!(model_numbers.iter().count(), 0);
+
+    blocks[13].div_z = 1;
+    assert_eq!(
+        ModelNumbers::from_blocks(&blocks).unwrap_err(),
+        "block 13: push block with add x -20"
+    );
+}
+
+fn test_debugger(input: &HashMap<String, Vec<Command>>) {
+    // trace every step of a short program
+    let negate = input.get("negate").unwrap();
+    let mut debugger = Debugger::concrete(negate, &[10]);
+    assert_eq!(debugger.run(), Ok(StopReason::Finished));
+    assert_eq!(debugger.trace.len(), negate.len());
+    assert_eq!(
+        debugger.trace.last().unwrap(),
+        "   1 mul x -1   w=0 x=-10 y=0 z=0"
+    );
+
+    let get_bits = input.get("get_bits").unwrap();
+    let mut debugger = Debugger::concrete(get_bits, &[0b1010]);
+    debugger.add_breakpoint(Breakpoint::Instruction(0));
+    debugger.add_breakpoint(Breakpoint::Instruction(2));
+    // stops before the first instruction, running again does not stop at the same place
+    assert_eq!(debugger.run(), Ok(StopReason::Breakpoint(0)));
+    assert!(debugger.trace.is_empty());
+    assert_eq!(debugger.run(), Ok(StopReason::Breakpoint(2)));
+    assert_eq!(
+        debugger.trace,
+        vec![
+            "   0 inp w      w=10 x=0 y=0 z=0",
+            "   1 add z w    w=10 x=0 y=0 z=10"
+        ]
+    );
+    assert_eq!(debugger.step(), Ok(true));
+    assert_eq!(
+        debugger.trace.last().unwrap(),
+        "   2 mod z 2    w=10 x=0 y=0 z=0"
+    );
+    assert_eq!(debugger.run(), Ok(StopReason::Finished));
+    assert_eq!(debugger.trace.len(), get_bits.len());
+    assert_eq!(debugger.step(), Ok(false));
+    assert_eq!(
+        debugger.trace.last().unwrap(),
+        "  10 mod w 2    w=1 x=0 y=1 z=0"
+    );
+
+    // echo prints the same lines while running
+    let mut out = Vec::new();
+    let mut debugger = Debugger::concrete(get_bits, &[0b1010]).with_echo(&mut out);
+    debugger.add_breakpoint(Breakpoint::Instruction(2));
+    assert_eq!(debugger.run(), Ok(StopReason::Breakpoint(2)));
+    assert_eq!(debugger.run(), Ok(StopReason::Finished));
+    let trace = debugger.trace;
+    assert_eq!(String::from_utf8(out).unwrap(), trace.join("\n") + "\n");
+
+    // faults are reported from step
+    let div = parse_program("inp x\ninp y\ndiv x y");
+    let mut debugger = Debugger::concrete(&div, &[1, 0]);
+    assert_eq!(debugger.run(), Err(AluError::DivByZero(2)));
+
+    // stop before every digit, range trace should agree with run_program
+    let day24 = input.get("day24").unwrap();
+    let digits = vec![Range::new(1, 9); 14];
+    let mut debugger = Debugger::ranges(day24, &digits);
+    debugger.add_breakpoint(Breakpoint::Inp);
+    let mut stops = 0;
+    while let Ok(StopReason::Breakpoint(pos)) = debugger.run() {
+        assert!(matches!(day24[pos], Command::Inp(_)));
+        stops += 1;
+    }
+    assert_eq!(stops, 14);
+    assert!(debugger.is_finished());
+    match &debugger.machine {
+        Machine::Ranges(state, _) => assert_eq!(*state, run_program(day24, &digits)),
+        Machine::Concrete(..) => unreachable!(),
+    }
+    assert!(debugger.trace.last().unwrap().contains("z=["));
+}
+
+fn test_concrete(input: &HashMap<String, Vec<Command>>) {
+    assert_eq!(
+        run_program_concrete(input.get("negate").unwrap(), &[10]),
+        Ok([0, -10, 0, 0])
+    );
+    let is_3_times_bigger = input.get("is_3_times_bigger").unwrap();
+    assert_eq!(
+        run_program_concrete(is_3_times_bigger, &[10, 30]).unwrap()[3],
+        1
+    );
+    assert_eq!(
+        run_program_concrete(is_3_times_bigger, &[10, 31]).unwrap()[3],
+        0
+    );
+    let get_bits = input.get("get_bits").unwrap();
+    assert_eq!(run_program_concrete(get_bits, &[0b1010]), Ok([1, 0, 1, 0]));
+    assert_eq!(run_program_concrete(get_bits, &[0b0101]), Ok([0, 1, 0, 1]));
+
+    // division truncates toward zero
+    let div = parse_program("inp x\ninp y\ndiv x y");
+    assert_eq!(run_program_concrete(&div, &[7, 2]), Ok([0, 3, 2, 0]));
+    assert_eq!(run_program_concrete(&div, &[-7, 2]), Ok([0, -3, 2, 0]));
+
+    // faults
+    assert_eq!(
+        run_program_concrete(&div, &[7, 0]),
+        Err(AluError::DivByZero(2))
+    );
+    assert_eq!(run_program_concrete(&div, &[7]), Err(AluError::NoInput(1)));
+    let modulo = parse_program("inp x\ninp y\nmod x y");
+    assert_eq!(run_program_concrete(&modulo, &[7, 3]), Ok([0, 1, 3, 0]));
+    assert_eq!(
+        run_program_concrete(&modulo, &[7, 0]),
+        Err(AluError::ModByZero(2))
+    );
+    assert_eq!(
+        run_program_concrete(&modulo, &[-7, 3]),
+        Err(AluError::ModNegative(2))
+    );
+    assert_eq!(
+        run_program_concrete(&modulo, &[7, -3]),
+        Err(AluError::ModNegative(2))
+    );
+    let overflow = parse_program("inp x\ninp y\nadd x y\nmul x y");
+    assert_eq!(
+        run_program_concrete(&overflow, &[i64::MAX, 1]),
+        Err(AluError::Overflow(2))
+    );
+    assert_eq!(
+        run_program_concrete(&overflow, &[i64::MAX / 2, 3]),
+        Err(AluError::Overflow(3))
+    );
+    assert_eq!(
+        run_program_concrete(&div, &[i64::MIN, -1]),
+        Err(AluError::Overflow(2))
+    );
+    assert_eq!(
+        AluError::ModNegative(2).to_string(),
+        "modulo with negative value at instruction 2"
+    );
+
+    assert_eq!(
+        number_to_digits(13579246899999),
+        vec![1, 3, 5, 7, 9, 2, 4, 6, 8, 9, 9, 9, 9, 9]
+    );
+    assert_eq!(
+        is_valid_model_number(input.get("day24").unwrap(), 13579246899999),
+        Ok(false)
+    );
 }
 
 fn parse_input(s: &str) -> HashMap<String, Vec<Command>> {
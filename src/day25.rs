@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::grid::Grid;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum NodeType {
     Empty,
@@ -95,60 +97,43 @@ impl std::fmt::Debug for Node {
 
 #[derive(Clone)]
 struct State {
-    size_x: usize,
-    size_y: usize,
     generation: usize,
-    data: Vec<Node>,
+    grid: Grid<Node>, // sea cucumbers leaving one edge appear on the opposite edge
 }
 
 impl PartialEq for State {
     fn eq(&self, other: &Self) -> bool {
-        if self.size_x != other.size_x || self.size_y != other.size_y {
+        if self.grid.size_x() != other.grid.size_x() || self.grid.size_y() != other.grid.size_y() {
             return false;
         }
-        for y in 0..self.size_y {
-            for x in 0..self.size_x {
-                if self.read_current(x, y) != other.read_current(x, y) {
-                    return false;
-                }
-            }
-        }
-
-        true
+        self.grid
+            .coords()
+            .all(|(x, y)| self.read_current(x, y) == other.read_current(x, y))
     }
 }
 
 impl State {
     fn parse(lines: &[&str]) -> State {
-        let size_y = lines.len();
-        let size_x = if size_y > 0 { lines[0].len() } else { 0 };
-        let data = vec![Node::new(); size_x * size_y];
-        let mut state = State {
-            size_x,
-            size_y,
-            generation: 0,
-            data,
-        };
-        lines.iter().enumerate().for_each(|(y, &line)| {
-            line.chars().enumerate().for_each(|(x, c)| {
-                state.set_next(
-                    x,
-                    y,
-                    match c {
-                        '.' => NodeType::Empty,
-                        '>' => NodeType::CucuEast,
-                        'v' => NodeType::CucuSouth,
-                        _ => panic!("unexpected char at line {} pos {}", y, x),
-                    },
-                );
-            })
+        let generation = 1;
+        let grid = Grid::parse_lines(lines.iter().cloned(), |c| {
+            let mut node = Node::new();
+            node.set_generation(generation);
+            node.set_type(match c {
+                '.' => NodeType::Empty,
+                '>' => NodeType::CucuEast,
+                'v' => NodeType::CucuSouth,
+                _ => panic!("unexpected char {}", c),
+            });
+            node
         });
-        state.inc_generation();
-        state
+        State {
+            generation,
+            grid: grid.with_wrap(),
+        }
     }
 
     fn read_current(&self, x: usize, y: usize) -> NodeType {
-        let node = self.data[x + y * self.size_x];
+        let node = self.grid[(x, y)];
         if node.get_generation() == self.generation {
             node.get_type()
         } else {
@@ -157,16 +142,12 @@ impl State {
     }
 
     fn is_empty_current_and_next(&self, x: usize, y: usize) -> bool {
-        let node = self.data[x + y * self.size_x];
-        if node.get_type() == NodeType::Empty || node.get_generation() < self.generation {
-            true
-        } else {
-            false
-        }
+        let node = self.grid[(x, y)];
+        node.get_type() == NodeType::Empty || node.get_generation() < self.generation
     }
 
     fn set_next(&mut self, x: usize, y: usize, typ: NodeType) {
-        let node = &mut self.data[x + y * self.size_x];
+        let node = &mut self.grid[(x, y)];
         node.set_generation(self.generation + 1);
         node.set_type(typ);
     }
@@ -178,21 +159,19 @@ impl State {
     fn step(&mut self) -> bool {
         let mut has_moved: bool = false;
         for (move_typ, dx, dy) in [(NodeType::CucuEast, 1, 0), (NodeType::CucuSouth, 0, 1)] {
-            for y in 0..self.size_y {
-                let move_y = (y + dy) % self.size_y;
-                for x in 0..self.size_x {
-                    let move_x = (x + dx) % self.size_x;
-                    let typ = self.read_current(x, y);
-                    if typ != NodeType::Empty {
-                        let (new_x, new_y) =
-                            if typ == move_typ && self.is_empty_current_and_next(move_x, move_y) {
-                                has_moved = true;
-                                (move_x, move_y)
-                            } else {
-                                (x, y)
-                            };
-                        self.set_next(new_x, new_y, typ);
-                    }
+            for (x, y) in self.grid.coords() {
+                let typ = self.read_current(x, y);
+                if typ != NodeType::Empty {
+                    let (move_x, move_y) =
+                        self.grid.resolve(x as isize + dx, y as isize + dy).unwrap();
+                    let (new_x, new_y) =
+                        if typ == move_typ && self.is_empty_current_and_next(move_x, move_y) {
+                            has_moved = true;
+                            (move_x, move_y)
+                        } else {
+                            (x, y)
+                        };
+                    self.set_next(new_x, new_y, typ);
                 }
             }
             self.inc_generation();
@@ -211,11 +190,11 @@ impl State {
 
 impl std::fmt::Debug for State {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for y in 0..self.size_y {
-            for x in 0..self.size_x {
+        for y in 0..self.grid.size_y() {
+            for x in 0..self.grid.size_x() {
                 write!(f, "{}", self.read_current(x, y).to_string())?
             }
-            writeln!(f, "")?
+            writeln!(f)?
         }
         std::fmt::Result::Ok(())
     }
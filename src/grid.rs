@@ -0,0 +1,278 @@
+// 2d grid shared by days that work with char maps
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+const NEIGHBOURS4: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const NEIGHBOURS8: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+#[derive(Clone, PartialEq)]
+pub struct Grid<T> {
+    size_x: usize,
+    size_y: usize,
+    data: Vec<T>,       // data[x + y * size_x]
+    wrap: bool,         // coordinates outside of the grid wrap around to the other side
+    outside: Option<T>, // value of every cell outside of the grid, for grids on infinite background
+}
+
+impl<T> Grid<T> {
+    pub fn from_vec(size_x: usize, size_y: usize, data: Vec<T>) -> Grid<T> {
+        assert_eq!(data.len(), size_x * size_y);
+        Grid {
+            size_x,
+            size_y,
+            data,
+            wrap: false,
+            outside: None,
+        }
+    }
+
+    // every char of non empty trimmed line is converted to a cell
+    pub fn parse<F>(s: &str, f: F) -> Grid<T>
+    where
+        F: FnMut(char) -> T,
+    {
+        Self::parse_lines(s.lines(), f)
+    }
+
+    pub fn parse_lines<'a, I, F>(lines: I, mut f: F) -> Grid<T>
+    where
+        I: IntoIterator<Item = &'a str>,
+        F: FnMut(char) -> T,
+    {
+        let mut size_x = None;
+        let mut size_y = 0;
+        let mut data = Vec::new();
+        for line in lines
+            .into_iter()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+        {
+            let line_size_x = line.chars().count();
+            match size_x {
+                None => size_x = Some(line_size_x),
+                Some(size_x) => assert_eq!(size_x, line_size_x, "line {}", line),
+            }
+            data.extend(line.chars().map(&mut f));
+            size_y += 1;
+        }
+        Self::from_vec(size_x.unwrap_or(0), size_y, data)
+    }
+
+    pub fn with_wrap(mut self) -> Grid<T> {
+        assert!(self.outside.is_none());
+        self.wrap = true;
+        self
+    }
+
+    pub fn with_outside(mut self, value: T) -> Grid<T> {
+        self.set_outside(value);
+        self
+    }
+
+    pub fn set_outside(&mut self, value: T) {
+        assert!(!self.wrap);
+        self.outside = Some(value);
+    }
+
+    pub fn outside(&self) -> Option<&T> {
+        self.outside.as_ref()
+    }
+
+    pub fn size_x(&self) -> usize {
+        self.size_x
+    }
+
+    pub fn size_y(&self) -> usize {
+        self.size_y
+    }
+
+    // converts any coordinates to coordinates of a cell inside the grid, if there is such cell
+    pub fn resolve(&self, x: isize, y: isize) -> Option<(usize, usize)> {
+        if self.wrap {
+            if self.size_x == 0 || self.size_y == 0 {
+                return None;
+            }
+            Some((
+                x.rem_euclid(self.size_x as isize) as usize,
+                y.rem_euclid(self.size_y as isize) as usize,
+            ))
+        } else if x >= 0 && y >= 0 && (x as usize) < self.size_x && (y as usize) < self.size_y {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    // cell value, or background value for cells outside of the grid
+    pub fn get(&self, x: isize, y: isize) -> Option<&T> {
+        match self.resolve(x, y) {
+            Some(pos) => Some(&self[pos]),
+            None => self.outside.as_ref(),
+        }
+    }
+
+    pub fn get_mut(&mut self, x: isize, y: isize) -> Option<&mut T> {
+        self.resolve(x, y).map(move |pos| &mut self[pos])
+    }
+
+    pub fn coords(&self) -> impl Iterator<Item = (usize, usize)> {
+        let size_x = self.size_x;
+        (0..self.size_y).flat_map(move |y| (0..size_x).map(move |x| (x, y)))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.data.iter_mut()
+    }
+
+    // up, down, left and right neighbours that are inside the grid
+    pub fn neighbours4(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbours(x, y, &NEIGHBOURS4)
+    }
+
+    // same as neighbours4 plus diagonal neighbours
+    pub fn neighbours8(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbours(x, y, &NEIGHBOURS8)
+    }
+
+    fn neighbours<'a>(
+        &'a self,
+        x: usize,
+        y: usize,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let resolve =
+            move |(dx, dy): &(isize, isize)| self.resolve(x as isize + dx, y as isize + dy);
+        offsets.iter().enumerate().filter_map(move |(i, offset)| {
+            let pos = resolve(offset)?;
+            // on wrapped grids smaller than 3 cells some offsets lead back to the cell itself
+            // or to the same neighbour as an earlier offset
+            let repeated = self.wrap
+                && (pos == (x, y)
+                    || offsets[..i]
+                        .iter()
+                        .any(|offset| resolve(offset) == Some(pos)));
+            (!repeated).then_some(pos)
+        })
+    }
+
+    pub fn render<F>(&self, mut f: F) -> String
+    where
+        F: FnMut(&T) -> char,
+    {
+        let mut result = String::new();
+        for y in 0..self.size_y {
+            for x in 0..self.size_x {
+                result.push(f(&self[(x, y)]));
+            }
+            result.push('\n');
+        }
+        result
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(size_x: usize, size_y: usize, value: T) -> Grid<T> {
+        Self::from_vec(size_x, size_y, vec![value; size_x * size_y])
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(
+            x < self.size_x && y < self.size_y,
+            "bad coordinates {} {}",
+            x,
+            y
+        );
+        &self.data[x + y * self.size_x]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        assert!(
+            x < self.size_x && y < self.size_y,
+            "bad coordinates {} {}",
+            x,
+            y
+        );
+        &mut self.data[x + y * self.size_x]
+    }
+}
+
+impl<T: fmt::Display> fmt::Debug for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.size_y {
+            for x in 0..self.size_x {
+                write!(f, "{}", self[(x, y)])?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn main() {
+    let grid = Grid::parse(
+        "
+        123
+        456
+        ",
+        |c| c.to_digit(10).unwrap(),
+    );
+    assert_eq!((grid.size_x(), grid.size_y()), (3, 2));
+    assert_eq!(grid[(2, 1)], 6);
+    assert_eq!(grid.get(-1, 0), None);
+    assert_eq!(format!("{:?}", grid), "123\n456\n");
+    assert_eq!(
+        grid.render(|&v| if v % 2 == 0 { '#' } else { '.' }),
+        ".#.\n#.#\n"
+    );
+
+    let mut neighbours = grid.neighbours4(0, 0).collect::<Vec<_>>();
+    neighbours.sort();
+    assert_eq!(neighbours, vec![(0, 1), (1, 0)]);
+    assert_eq!(grid.neighbours8(1, 0).count(), 5);
+    assert_eq!(grid.neighbours8(1, 1).count(), 5);
+    assert_eq!(grid.coords().count(), 6);
+
+    let wrapped = grid.clone().with_wrap();
+    assert_eq!(wrapped.get(-1, 0), Some(&3));
+    assert_eq!(wrapped.get(3, 2), Some(&1));
+    // cells above and below are the same row of a 2 rows grid
+    assert_eq!(wrapped.neighbours4(0, 0).count(), 3);
+
+    let background = grid.with_outside(0);
+    assert_eq!(background.outside(), Some(&0));
+    assert_eq!(background.get(-1, 0), Some(&0));
+    assert_eq!(background.get(100, 100), Some(&0));
+    assert_eq!(background.get(1, 1), Some(&5));
+
+    let empty = Grid::<u32>::new(0, 0, 0).with_wrap();
+    assert_eq!(empty.get(1, 1), None);
+    assert_eq!(empty.neighbours8(0, 0).count(), 0);
+
+    let small = Grid::parse("12\n34", |c| c.to_digit(10).unwrap()).with_wrap();
+    let mut neighbours = small.neighbours8(0, 0).collect::<Vec<_>>();
+    neighbours.sort();
+    assert_eq!(neighbours, vec![(0, 1), (1, 0), (1, 1)]);
+    assert_eq!(small.neighbours4(1, 1).count(), 2);
+    let single = Grid::new(1, 1, 0).with_wrap();
+    assert_eq!(single.neighbours8(0, 0).count(), 0);
+    println!("grid tests ok");
+}
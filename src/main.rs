@@ -18,16 +18,24 @@ mod day17;
 mod day18;
 mod day19;
 mod day20;
-mod day21pt1;
 mod day21;
 mod day22;
 mod day23;
 mod day24;
 mod day25;
+mod grid;
 
 fn main() {
+    // cargo run -- play23 [map name] [unfold]
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("play23") {
+        day23::play_interactive(&args[1..]);
+        return;
+    }
+
     let run_all = false;
     if run_all {
+        grid::main();
         day1::main();
         day2::main().unwrap();
         day3::main();
@@ -48,7 +56,6 @@ fn main() {
         day18::main();
         day19::main();
         day20::main();
-        day21pt1::main();
         day21::main();
         day22::main();
         day23::main();